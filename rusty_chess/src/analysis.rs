@@ -0,0 +1,112 @@
+use rusty_chess::board::movegen::Move;
+use rusty_chess::board::pgn::movetext;
+use rusty_chess::board::piece::Owner;
+use rusty_chess::board::position::Position;
+use rusty_chess::engine::search::{AnalysisThread, SearchInfo};
+use rusty_chess::engine::tt::TranspositionTable;
+use raylib::prelude::*;
+use std::sync::{Arc, Mutex};
+
+// How many of the best moves are shown, each with the line it leads to
+const LINES: usize = 3;
+const LINE_HEIGHT: f32 = 30.0;
+
+// The engine's running commentary on the position on the board
+pub struct Analysis {
+    search: Option<AnalysisThread>,
+    position: Position,
+    // Best first, from the deepest iteration so far
+    lines: Vec<SearchInfo>,
+}
+
+impl Analysis {
+    pub fn new() -> Analysis {
+        Analysis { search: None, position: Position::default(), lines: Vec::new() }
+    }
+
+    // Keep the search going, starting over whenever the position on the board changes
+    pub fn update(&mut self, position: &Position, history: &[Position], table: &Arc<Mutex<TranspositionTable>>) {
+        if self.search.is_none() || position.hash != self.position.hash {
+            // The old search lets go of the table once it sees it has been stopped
+            self.search = None;
+            self.lines.clear();
+            self.position = *position;
+            self.search = Some(AnalysisThread::spawn(*position, history.to_vec(), LINES, Arc::clone(table)));
+        }
+        if let Some(lines) = self.search.as_ref().and_then(AnalysisThread::try_lines) {
+            self.lines = lines;
+        }
+    }
+
+    pub fn depth(&self) -> Option<u32> {
+        self.lines.first().map(|line| line.depth)
+    }
+
+    pub fn best_move(&self) -> Option<Move> {
+        self.lines.first().and_then(SearchInfo::best_move)
+    }
+
+    // Scores are kept from the side to move's point of view, but shown from white's
+    fn white_score(&self, line: &SearchInfo) -> i32 {
+        match self.position.turn {
+            Owner::White => line.score,
+            Owner::Black => -line.score,
+        }
+    }
+
+    // "+0.35" in pawns, or "M3" for a mate, "-M3" when black mates
+    fn format_score(&self, line: &SearchInfo) -> String {
+        let white_score = self.white_score(line);
+        match line.mate_in() {
+            Some(_) if line.pv.is_empty() => "#".to_string(),
+            Some(moves) => format!("{}M{}", if white_score < 0 { "-" } else { "" }, moves.abs()),
+            None => format!("{:+.2}", white_score as f32 / 100.0),
+        }
+    }
+
+    // White's share of the bar, which fills from white's side of the board.
+    // A pawn up is about a 64% share, mates fill it all.
+    fn white_share(&self) -> f32 {
+        let line = match self.lines.first() {
+            Some(line) => line,
+            None => return 0.5,
+        };
+        let white_score = self.white_score(line);
+        if line.mate_in().is_some() {
+            return if white_score > 0 { 1.0 } else { 0.0 };
+        }
+        1.0 / (1.0 + 10f32.powf(-white_score as f32 / 400.0))
+    }
+
+    pub fn draw_eval_bar(&self, d: &mut RaylibDrawHandle, rect: Rectangle, flipped: bool) {
+        d.draw_rectangle_rec(rect, Color::DARKGRAY);
+        let height = rect.height * self.white_share();
+        // White's end of the bar is the bottom unless the board is turned around
+        let y = if flipped { rect.y } else { rect.y + rect.height - height };
+        d.draw_rectangle_rec(Rectangle { y, height, ..rect }, Color::RAYWHITE);
+        d.draw_rectangle_lines_ex(rect, 1.0, Color::GRAY);
+    }
+
+    // One row per line: its score, then its moves for as long as they fit
+    pub fn draw_lines(&self, d: &mut RaylibDrawHandle, rect: Rectangle) {
+        d.draw_rectangle_lines_ex(rect, 1.0, Color::LIGHTGRAY);
+
+        for (i, line) in self.lines.iter().take(LINES).enumerate() {
+            let y = (rect.y + 6.0 + i as f32 * LINE_HEIGHT) as i32;
+            let score = self.format_score(line);
+            d.draw_text(&score, rect.x as i32 + 8, y, 20, Color::DARKBLUE);
+
+            let x = rect.x as i32 + 80;
+            let room = (rect.x + rect.width) as i32 - 8 - x;
+            let mut text = String::new();
+            for token in movetext(&self.position, &line.pv) {
+                let longer = if text.is_empty() { token } else { format!("{} {}", text, token) };
+                if d.measure_text(&longer, 20) > room {
+                    break;
+                }
+                text = longer;
+            }
+            d.draw_text(&text, x, y, 20, Color::BLACK);
+        }
+    }
+}
@@ -1,2 +1,15 @@
+pub mod bitboard;
+pub mod eco;
+pub mod fen;
+pub mod movegen;
+pub mod notation;
+pub mod outcome;
+pub mod perft;
+pub mod pgn;
 pub mod piece;
-pub mod space;
\ No newline at end of file
+pub mod polyglot;
+pub mod position;
+pub mod san;
+pub mod setup;
+pub mod square;
+pub mod zobrist;
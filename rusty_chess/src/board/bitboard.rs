@@ -0,0 +1,141 @@
+use crate::board::piece::{Owner, Piece, PieceType, ALL_DIRECTIONS, KNIGHT_JUMPS};
+use crate::board::square::Square;
+
+// A set of squares, bit `n` standing for `Square::from_index(n)`
+pub type Bitboard = u64;
+
+// The squares in a bitboard, lowest index first
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let index = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some(Square::from_index(index))
+    })
+}
+
+// Squares one step away from each square, for pieces that don't slide
+const fn step_attacks(steps: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut index = 0;
+    while index < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            if let Some(target) = Square::from_index(index).offset(steps[i].0, steps[i].1) {
+                table[index] |= target.bit();
+            }
+            i += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+// Every square from each square to the edge of the board, for each of `ALL_DIRECTIONS`
+const fn rays() -> [[Bitboard; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut dir = 0;
+    while dir < 8 {
+        let mut index = 0;
+        while index < 64 {
+            let mut current = Square::from_index(index);
+            while let Some(next) = current.offset(ALL_DIRECTIONS[dir].0, ALL_DIRECTIONS[dir].1) {
+                table[dir][index] |= next.bit();
+                current = next;
+            }
+            index += 1;
+        }
+        dir += 1;
+    }
+    table
+}
+
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = step_attacks(&KNIGHT_JUMPS);
+pub const KING_ATTACKS: [Bitboard; 64] = step_attacks(&ALL_DIRECTIONS);
+// Squares a pawn attacks, indexed by its owner. Black pawns head down the board.
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [step_attacks(&[(-1, 1), (1, 1)]), step_attacks(&[(-1, -1), (1, -1)])];
+const RAYS: [[Bitboard; 64]; 8] = rays();
+
+// A ray stopped at (and including) the first occupied square on it
+fn ray_attacks(square: Square, occupied: Bitboard, dir: usize) -> Bitboard {
+    let ray = RAYS[dir][square.index()];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+
+    // The nearest blocker is the lowest bit on rays heading up the indices,
+    // the highest on rays heading down
+    let (col, row) = ALL_DIRECTIONS[dir];
+    let blocker = if row * 8 + col > 0 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[dir][blocker as usize]
+}
+
+// `ALL_DIRECTIONS` lists the straight directions first, then the diagonals
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    (0..4).fold(0, |attacks, dir| attacks | ray_attacks(square, occupied, dir))
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    (4..8).fold(0, |attacks, dir| attacks | ray_attacks(square, occupied, dir))
+}
+
+// Squares `piece` on `square` attacks, given which squares are occupied.
+// For pawns that is only their diagonal captures.
+pub fn attacks(piece: Piece, square: Square, occupied: Bitboard) -> Bitboard {
+    match piece.piece_type {
+        PieceType::Pawn => PAWN_ATTACKS[piece.owner as usize][square.index()],
+        PieceType::Knight => KNIGHT_ATTACKS[square.index()],
+        PieceType::Bishop => bishop_attacks(square, occupied),
+        PieceType::Rook => rook_attacks(square, occupied),
+        PieceType::Queen => bishop_attacks(square, occupied) | rook_attacks(square, occupied),
+        PieceType::King => KING_ATTACKS[square.index()],
+    }
+}
+
+// The rows pawns start on and promote on, for each owner
+pub fn pawn_rows(owner: Owner) -> (usize, usize) {
+    match owner {
+        Owner::White => (6, 0),
+        Owner::Black => (1, 7),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bits(names: &[&str]) -> Bitboard {
+        names.iter().fold(0, |bitboard, name| bitboard | Square::parse(name).unwrap().bit())
+    }
+
+    #[test]
+    fn test_step_attacks() {
+        let a1 = Square::parse("a1").unwrap();
+        assert_eq!(KNIGHT_ATTACKS[a1.index()], bits(&["b3", "c2"]));
+        assert_eq!(KING_ATTACKS[a1.index()], bits(&["a2", "b2", "b1"]));
+        assert_eq!(PAWN_ATTACKS[Owner::White as usize][Square::parse("e4").unwrap().index()], bits(&["d5", "f5"]));
+    }
+
+    #[test]
+    fn test_sliding_attacks_stop_at_blockers() {
+        let d4 = Square::parse("d4").unwrap();
+        let occupied = bits(&["d6", "b4", "f6", "c3"]);
+
+        assert_eq!(
+            rook_attacks(d4, occupied),
+            bits(&["d5", "d6", "d3", "d2", "d1", "c4", "b4", "e4", "f4", "g4", "h4"])
+        );
+        assert_eq!(
+            bishop_attacks(d4, occupied),
+            bits(&["e5", "f6", "c5", "b6", "a7", "c3", "e3", "f2", "g1"])
+        );
+        assert_eq!(squares(bits(&["a8", "h1"])).collect::<Vec<_>>(), [Square::from_index(0), Square::from_index(63)]);
+    }
+}
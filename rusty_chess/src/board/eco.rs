@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::board::position::Position;
+use crate::board::san::SanError;
+
+// The names of the common openings, one per line as "<ECO code>\t<name>\t<moves>"
+const BUILT_IN: &str = include_str!("eco.tsv");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcoError {
+    // `line` counts from 1
+    MissingField { line: usize },
+    InvalidMove { line: usize, error: SanError },
+}
+
+impl fmt::Display for EcoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EcoError::MissingField { line } => write!(f, "line {}: expected a code, a name and moves", line),
+            EcoError::InvalidMove { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for EcoError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opening {
+    // Encyclopaedia of Chess Openings code, like "B90"
+    pub eco: String,
+    pub name: String,
+}
+
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.eco, self.name)
+    }
+}
+
+// Openings by the position their moves lead to, so a game that gets there
+// by a different move order is still named
+pub struct EcoTable {
+    openings: HashMap<u64, Opening>,
+}
+
+impl EcoTable {
+    // Tab separated lines of code, name and moves, the moves in SAN with or
+    // without move numbers. A later line for the same position replaces an earlier one.
+    pub fn parse(text: &str) -> Result<EcoTable, EcoError> {
+        let mut openings = HashMap::new();
+
+        for (index, text) in text.lines().enumerate().filter(|(_, text)| !text.trim().is_empty()) {
+            let line = index + 1;
+            let mut fields = text.split('\t');
+            let (eco, name, moves) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(eco), Some(name), Some(moves)) => (eco.trim(), name.trim(), moves),
+                _ => return Err(EcoError::MissingField { line }),
+            };
+
+            let mut position = Position::default();
+            for san in moves.split_whitespace().filter(|token| !token.ends_with('.')) {
+                let mv = position.parse_san(san).map_err(|error| EcoError::InvalidMove { line, error })?;
+                position.apply_move(mv);
+            }
+            openings.insert(position.hash, Opening { eco: eco.to_string(), name: name.to_string() });
+        }
+
+        Ok(EcoTable { openings })
+    }
+
+    pub fn built_in() -> EcoTable {
+        // The table is checked by the tests below
+        EcoTable::parse(BUILT_IN).unwrap()
+    }
+
+    pub fn lookup(&self, position: &Position) -> Option<&Opening> {
+        self.openings.get(&position.hash)
+    }
+
+    // The opening a game is in, named after the last position along it that has a name
+    pub fn opening(&self, positions: &[Position]) -> Option<&Opening> {
+        positions.iter().rev().find_map(|position| self.lookup(position))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn play(moves: &[&str]) -> Vec<Position> {
+        let mut positions = vec![Position::default()];
+        for san in moves {
+            let mut next = *positions.last().unwrap();
+            next.apply_move(next.parse_san(san).unwrap());
+            positions.push(next);
+        }
+        positions
+    }
+
+    #[test]
+    fn test_built_in_table() {
+        let table = EcoTable::built_in();
+
+        let najdorf = table.opening(&play(&["e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6", "h3"])).unwrap();
+        assert_eq!(najdorf.to_string(), "B90 Sicilian Defence: Najdorf Variation");
+        assert!(table.opening(&play(&[])).is_none());
+    }
+
+    #[test]
+    fn test_transpositions_are_named() {
+        let table = EcoTable::parse("D06\tQueen's Gambit\t1. d4 d5 2. c4\n").unwrap();
+
+        let opening = table.opening(&play(&["c4", "d5", "d4"])).unwrap();
+        assert_eq!(opening.name, "Queen's Gambit");
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        assert_eq!(EcoTable::parse("A00\tPolish Opening").err(), Some(EcoError::MissingField { line: 1 }));
+        assert!(matches!(EcoTable::parse("\nA00\tNonsense\t1. e5").err(), Some(EcoError::InvalidMove { line: 2, .. })));
+    }
+}
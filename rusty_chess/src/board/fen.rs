@@ -0,0 +1,213 @@
+use std::fmt;
+
+use crate::board::piece::{Owner, Piece};
+use crate::board::position::{CastlingRights, Position};
+use crate::board::square::Square;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    // FEN needs at least the board, side to move, castling and en passant fields
+    WrongFieldCount(usize),
+    InvalidPiece(char),
+    // A rank that doesn't describe exactly 8 spaces, or not 8 ranks at all
+    InvalidBoard(String),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidClock(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(n) => write!(f, "expected 4 to 6 FEN fields, found {}", n),
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a piece", c),
+            FenError::InvalidBoard(board) => write!(f, "'{}' does not describe an 8x8 board", board),
+            FenError::InvalidSideToMove(side) => write!(f, "side to move must be 'w' or 'b', found '{}'", side),
+            FenError::InvalidCastling(castling) => write!(f, "invalid castling rights '{}'", castling),
+            FenError::InvalidEnPassant(square) => write!(f, "invalid en passant square '{}'", square),
+            FenError::InvalidClock(clock) => write!(f, "invalid move clock '{}'", clock),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Position {
+
+    // The move clocks may be left off, as they are in EPD, and default to "0 1"
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if !(4..=6).contains(&fields.len()) {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let mut position = Position::empty();
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::InvalidBoard(fields[0].to_string()));
+        }
+        for (row, rank) in ranks.iter().enumerate() {
+            let mut col = 0;
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    col += skip as usize;
+                } else {
+                    let piece = Piece::from_fen_char(c).ok_or(FenError::InvalidPiece(c))?;
+                    if col >= 8 {
+                        return Err(FenError::InvalidBoard(fields[0].to_string()));
+                    }
+                    position.set_piece(Square::new(col, row), Some(piece));
+                    col += 1;
+                }
+            }
+            if col != 8 {
+                return Err(FenError::InvalidBoard(fields[0].to_string()));
+            }
+        }
+
+        position.turn = match fields[1] {
+            "w" => Owner::White,
+            "b" => Owner::Black,
+            other => return Err(FenError::InvalidSideToMove(other.to_string())),
+        };
+
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                match c {
+                    'K' => position.castling.white_king_side = true,
+                    'Q' => position.castling.white_queen_side = true,
+                    'k' => position.castling.black_king_side = true,
+                    'q' => position.castling.black_queen_side = true,
+                    _ => return Err(FenError::InvalidCastling(fields[2].to_string())),
+                }
+            }
+        }
+
+        // The square must be one the side to move could take on, just behind
+        // a pawn of the other side that has moved two squares
+        if fields[3] != "-" {
+            match Square::parse(fields[3]) {
+                Some(square) if position.en_passant_fits(square) => position.en_passant = Some(square),
+                _ => return Err(FenError::InvalidEnPassant(fields[3].to_string())),
+            }
+        }
+
+        if let Some(clock) = fields.get(4) {
+            position.halfmove_clock = clock.parse().map_err(|_| FenError::InvalidClock(clock.to_string()))?;
+        }
+        if let Some(clock) = fields.get(5) {
+            position.fullmove_number = clock.parse().map_err(|_| FenError::InvalidClock(clock.to_string()))?;
+        }
+
+        position.refresh_hash();
+        Ok(position)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut ranks = Vec::new();
+        for row in 0..8 {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for col in 0..8 {
+                match self.piece_at(Square::new(col, row)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            rank.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        rank.push(piece.to_fen_char());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            ranks.push(rank);
+        }
+
+        let turn = match self.turn {
+            Owner::White => "w",
+            Owner::Black => "b",
+        };
+
+        let en_passant = match self.en_passant {
+            Some(square) => square.to_string(),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{} {} {} {} {} {}",
+            ranks.join("/"),
+            turn,
+            self.castling.to_fen(),
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
+
+impl CastlingRights {
+    pub fn to_fen(&self) -> String {
+        let mut rights = String::new();
+        for (allowed, c) in [
+            (self.white_king_side, 'K'),
+            (self.white_queen_side, 'Q'),
+            (self.black_king_side, 'k'),
+            (self.black_queen_side, 'q'),
+        ] {
+            if allowed {
+                rights.push(c);
+            }
+        }
+
+        if rights.is_empty() {
+            rights.push('-');
+        }
+        rights
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::movegen::Move;
+
+    #[test]
+    fn test_round_trip() {
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(Position::from_fen(kiwipete).unwrap().to_fen(), kiwipete);
+        assert_eq!(Position::default().to_fen(), STARTING_FEN);
+    }
+
+    #[test]
+    fn test_fen_after_double_push() {
+        let mut position = Position::default();
+        position.apply_move(Move::new(Square::new(4, 6), Square::new(4, 4)));
+
+        assert_eq!(position.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    }
+
+    #[test]
+    fn test_invalid_fen() {
+        assert_eq!(Position::from_fen("8/8/8/8 w - -"), Err(FenError::InvalidBoard("8/8/8/8".to_string())));
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/7x w - -"), Err(FenError::InvalidPiece('x')));
+        assert_eq!(
+            Position::from_fen("8/8/8/8/8/8/8/8 white - -"),
+            Err(FenError::InvalidSideToMove("white".to_string()))
+        );
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/8 w"), Err(FenError::WrongFieldCount(2)));
+
+        // En passant squares that don't fit the side to move or the pawns
+        let invalid = |square: &str| Err(FenError::InvalidEnPassant(square.to_string()));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - e3 0 1"), invalid("e3"));
+        assert_eq!(Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1"), invalid("e3"));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K3 b - e3 0 1"), invalid("e3"));
+        assert!(Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").is_ok());
+    }
+}
@@ -0,0 +1,335 @@
+use crate::board::bitboard::{attacks, bishop_attacks, pawn_rows, rook_attacks, squares, KING_ATTACKS, KNIGHT_ATTACKS, PAWN_ATTACKS};
+use crate::board::piece::{Owner, Piece, PieceType};
+use crate::board::position::Position;
+use crate::board::square::Square;
+use crate::board::zobrist::{castling_key, turn_key};
+
+// Pieces a pawn may turn into when it reaches the far side of the board
+pub const PROMOTION_CHOICES: [PieceType; 4] = [PieceType::Queen, PieceType::Knight, PieceType::Rook, PieceType::Bishop];
+
+// Castling and en passant are recognised from the position they are played in:
+// castling is the king moving two columns, en passant a pawn capturing onto
+// `Position::en_passant`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move { from, to, promotion: None }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, piece_type: PieceType) -> Move {
+        Move { from, to, promotion: Some(piece_type) }
+    }
+}
+
+impl Position {
+
+    // Every move the side to move can make without leaving its own king in check
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|mv| !self.leaves_king_in_check(*mv))
+            .collect()
+    }
+
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Move> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from == from)
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves().contains(&mv)
+    }
+
+    pub fn king_square(&self, owner: Owner) -> Option<Square> {
+        squares(self.bitboard(owner, PieceType::King)).next()
+    }
+
+    // Is the side to move in check
+    pub fn in_check(&self) -> bool {
+        match self.king_square(self.turn) {
+            Some(king) => self.is_attacked(king, self.turn.opponent()),
+            None => false,
+        }
+    }
+
+    // Could any piece of `by` capture on `square` if it were their move
+    pub fn is_attacked(&self, square: Square, by: Owner) -> bool {
+        let occupied = self.occupied();
+        let index = square.index();
+        let queens = self.bitboard(by, PieceType::Queen);
+
+        // Attacks are symmetric: a knight on `square` would reach the knights attacking it,
+        // and a pawn of the other side would reach the attacking pawns
+        PAWN_ATTACKS[by.opponent() as usize][index] & self.bitboard(by, PieceType::Pawn) != 0
+            || KNIGHT_ATTACKS[index] & self.bitboard(by, PieceType::Knight) != 0
+            || KING_ATTACKS[index] & self.bitboard(by, PieceType::King) != 0
+            || bishop_attacks(square, occupied) & (self.bitboard(by, PieceType::Bishop) | queens) != 0
+            || rook_attacks(square, occupied) & (self.bitboard(by, PieceType::Rook) | queens) != 0
+    }
+
+    // Whether the move takes a piece, en passant included
+    pub fn is_capture(&self, mv: Move) -> bool {
+        self.piece_at(mv.to).is_some()
+            || Some(mv.to) == self.en_passant
+                && self.piece_at(mv.from).map(|p| p.piece_type) == Some(PieceType::Pawn)
+    }
+
+    // Move a piece without checking legality, then pass the turn
+    pub fn apply_move(&mut self, mv: Move) {
+        let piece = match self.piece_at(mv.from) {
+            Some(p) => p,
+            None => return,
+        };
+        let mut captured = self.piece_at(mv.to);
+        // Take out the parts of the hash that set_piece doesn't look after, and put them back at the end
+        self.hash ^= castling_key(self.castling) ^ self.en_passant_key() ^ turn_key(self.turn);
+
+        // En passant takes the pawn beside the mover, not the one on the target
+        if piece.piece_type == PieceType::Pawn && Some(mv.to) == self.en_passant && mv.from.col() != mv.to.col() {
+            let taken = Square::new(mv.to.col(), mv.from.row());
+            captured = self.piece_at(taken);
+            self.set_piece(taken, None);
+        }
+
+        // Castling also brings the rook across to the other side of the king
+        if piece.piece_type == PieceType::King && mv.from.col().abs_diff(mv.to.col()) == 2 {
+            let (rook_from, rook_to) = if mv.to.col() > mv.from.col() { (7, 5) } else { (0, 3) };
+            let rook = self.piece_at(Square::new(rook_from, mv.from.row()));
+            self.set_piece(Square::new(rook_from, mv.from.row()), None);
+            self.set_piece(Square::new(rook_to, mv.from.row()), rook);
+        }
+
+        let placed = match mv.promotion {
+            Some(piece_type) => Piece::new(piece.owner, piece_type),
+            None => piece,
+        };
+        self.set_piece(mv.from, None);
+        self.set_piece(mv.to, Some(placed));
+
+        self.en_passant = None;
+        if piece.piece_type == PieceType::Pawn && mv.from.row().abs_diff(mv.to.row()) == 2 {
+            self.en_passant = Some(Square::new(mv.from.col(), (mv.from.row() + mv.to.row()) / 2));
+        }
+
+        if piece.piece_type == PieceType::King {
+            match piece.owner {
+                Owner::White => {
+                    self.castling.white_king_side = false;
+                    self.castling.white_queen_side = false;
+                }
+                Owner::Black => {
+                    self.castling.black_king_side = false;
+                    self.castling.black_queen_side = false;
+                }
+            }
+        }
+        // Moving a rook, or capturing one, loses castling on its side
+        for square in [mv.from, mv.to] {
+            match (square.col(), square.row()) {
+                (0, 7) => self.castling.white_queen_side = false,
+                (7, 7) => self.castling.white_king_side = false,
+                (0, 0) => self.castling.black_queen_side = false,
+                (7, 0) => self.castling.black_king_side = false,
+                _ => {}
+            }
+        }
+
+        if captured.is_some() || piece.piece_type == PieceType::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == Owner::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.opponent();
+        self.hash ^= castling_key(self.castling) ^ self.en_passant_key() ^ turn_key(self.turn);
+    }
+
+    fn leaves_king_in_check(&self, mv: Move) -> bool {
+        let mut after = *self;
+        after.apply_move(mv);
+
+        match after.king_square(self.turn) {
+            Some(king) => after.is_attacked(king, after.turn),
+            None => false,
+        }
+    }
+
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let own = self.occupied_by(self.turn);
+        let occupied = self.occupied();
+
+        for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King] {
+            let piece = Piece::new(self.turn, piece_type);
+            for from in squares(self.bitboard(self.turn, piece_type)) {
+                for to in squares(attacks(piece, from, occupied) & !own) {
+                    moves.push(Move::new(from, to));
+                }
+            }
+        }
+
+        if let Some(king) = self.king_square(self.turn) {
+            self.gen_castling_moves(king, self.turn, &mut moves);
+        }
+        for from in squares(self.bitboard(self.turn, PieceType::Pawn)) {
+            self.gen_pawn_moves(from, self.turn, &mut moves);
+        }
+
+        moves
+    }
+
+    fn gen_castling_moves(&self, from: Square, owner: Owner, moves: &mut Vec<Move>) {
+        let (home_row, king_side, queen_side) = match owner {
+            Owner::White => (7, self.castling.white_king_side, self.castling.white_queen_side),
+            Owner::Black => (0, self.castling.black_king_side, self.castling.black_queen_side),
+        };
+        let opponent = owner.opponent();
+        let rook = Some(Piece::new(owner, PieceType::Rook));
+        let empty = |cols: std::ops::Range<usize>| cols.into_iter().all(|col| self.piece_at(Square::new(col, home_row)).is_none());
+
+        if from != Square::new(4, home_row) || self.is_attacked(from, opponent) {
+            return;
+        }
+
+        // The spaces between king and rook must be empty, and the king may not
+        // pass over an attacked space. Landing in check is caught with the
+        // rest of the moves in `legal_moves`.
+        if king_side
+            && self.piece_at(Square::new(7, home_row)) == rook
+            && empty(5..7)
+            && !self.is_attacked(Square::new(5, home_row), opponent)
+        {
+            moves.push(Move::new(from, Square::new(6, home_row)));
+        }
+        if queen_side
+            && self.piece_at(Square::new(0, home_row)) == rook
+            && empty(1..4)
+            && !self.is_attacked(Square::new(3, home_row), opponent)
+        {
+            moves.push(Move::new(from, Square::new(2, home_row)));
+        }
+    }
+
+    fn gen_pawn_moves(&self, from: Square, owner: Owner, moves: &mut Vec<Move>) {
+        let forward = owner.forward();
+        let (start_row, last_row) = pawn_rows(owner);
+        let occupied = self.occupied();
+
+        // Reaching the last row means choosing what to promote to
+        let mut push = |to: Square| {
+            if to.row() == last_row {
+                for piece_type in PROMOTION_CHOICES {
+                    moves.push(Move::with_promotion(from, to, piece_type));
+                }
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        };
+
+        // Pushes only go to empty spaces, and the double push needs both free
+        if let Some(one) = from.offset(0, forward) {
+            if occupied & one.bit() == 0 {
+                push(one);
+
+                if from.row() == start_row {
+                    if let Some(two) = one.offset(0, forward) {
+                        if occupied & two.bit() == 0 {
+                            push(two);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut targets = self.occupied_by(owner.opponent());
+        if let Some(en_passant) = self.en_passant {
+            targets |= en_passant.bit();
+        }
+        for to in squares(PAWN_ATTACKS[owner as usize][from.index()] & targets) {
+            push(to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_opening_moves() {
+        let position = Position::default();
+
+        assert_eq!(position.legal_moves().len(), 20);
+        // Knight on b1 can only jump to a3 and c3
+        assert_eq!(position.legal_moves_from(Square::new(1, 7)).len(), 2);
+        assert!(position.is_legal(Move::new(Square::new(4, 6), Square::new(4, 4))));
+        assert!(!position.is_legal(Move::new(Square::new(4, 6), Square::new(4, 3))));
+    }
+
+    #[test]
+    fn test_pinned_piece_cannot_move() {
+        let mut position = Position::empty();
+        position.set_piece(Square::new(4, 7), Some(Piece::new(Owner::White, PieceType::King)));
+        position.set_piece(Square::new(4, 5), Some(Piece::new(Owner::White, PieceType::Knight)));
+        position.set_piece(Square::new(4, 0), Some(Piece::new(Owner::Black, PieceType::Rook)));
+        position.set_piece(Square::new(0, 0), Some(Piece::new(Owner::Black, PieceType::King)));
+
+        assert!(position.legal_moves_from(Square::new(4, 5)).is_empty());
+        assert!(!position.in_check());
+    }
+
+    #[test]
+    fn test_castling_moves_rook_and_clears_rights() {
+        let mut position = Position::default();
+        for col in [5, 6] {
+            position.set_piece(Square::new(col, 7), None);
+        }
+
+        let castle = Move::new(Square::new(4, 7), Square::new(6, 7));
+        assert!(position.is_legal(castle));
+
+        position.apply_move(castle);
+        assert_eq!(position.piece_at(Square::new(5, 7)), Some(Piece::new(Owner::White, PieceType::Rook)));
+        assert_eq!(position.piece_at(Square::new(7, 7)), None);
+        assert!(!position.castling.white_king_side);
+        assert!(!position.castling.white_queen_side);
+    }
+
+    #[test]
+    fn test_en_passant_and_promotion() {
+        let mut position = Position::empty();
+        position.set_piece(Square::new(4, 7), Some(Piece::new(Owner::White, PieceType::King)));
+        position.set_piece(Square::new(4, 0), Some(Piece::new(Owner::Black, PieceType::King)));
+        position.set_piece(Square::new(4, 3), Some(Piece::new(Owner::White, PieceType::Pawn)));
+        position.set_piece(Square::new(3, 1), Some(Piece::new(Owner::Black, PieceType::Pawn)));
+        position.set_piece(Square::new(0, 1), Some(Piece::new(Owner::White, PieceType::Pawn)));
+        position.turn = Owner::Black;
+
+        position.apply_move(Move::new(Square::new(3, 1), Square::new(3, 3)));
+        assert_eq!(position.en_passant, Some(Square::new(3, 2)));
+
+        let capture = Move::new(Square::new(4, 3), Square::new(3, 2));
+        assert!(position.is_legal(capture));
+        assert!(position.is_capture(capture));
+        assert!(!position.is_capture(Move::new(Square::new(4, 3), Square::new(4, 2))));
+        position.apply_move(capture);
+        assert_eq!(position.piece_at(Square::new(3, 3)), None);
+
+        position.turn = Owner::White;
+        assert!(!position.is_legal(Move::new(Square::new(0, 1), Square::new(0, 0))));
+        let promote = Move::with_promotion(Square::new(0, 1), Square::new(0, 0), PieceType::Knight);
+        assert!(position.is_legal(promote));
+        position.apply_move(promote);
+        assert_eq!(position.piece_at(Square::new(0, 0)), Some(Piece::new(Owner::White, PieceType::Knight)));
+    }
+}
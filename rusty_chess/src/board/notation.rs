@@ -0,0 +1,62 @@
+use crate::board::movegen::Move;
+use crate::board::piece::PieceType;
+use crate::board::position::Position;
+use crate::board::square::Square;
+
+// Moves written as the squares they go from and to, the way UCI, the network
+// protocol and the opening book tests write them
+
+impl Move {
+    // Long algebraic notation as UCI uses it, e.g. "e2e4" or "e7e8q"
+    pub fn to_uci(self) -> String {
+        let mut text = format!("{}{}", self.from, self.to);
+        if let Some(promotion) = self.promotion {
+            text.push(promotion.to_char().to_ascii_lowercase());
+        }
+        text
+    }
+
+    // Reads the notation without knowing the position, so the move may not be legal
+    pub fn from_uci(text: &str) -> Option<Move> {
+        if text.len() < 4 || text.len() > 5 || !text.is_ascii() {
+            return None;
+        }
+        let from = Square::parse(&text[0..2])?;
+        let to = Square::parse(&text[2..4])?;
+        let promotion = match text[4..].chars().next() {
+            Some(c) => Some(PieceType::from_char(c.to_ascii_uppercase())?),
+            None => None,
+        };
+        Some(Move { from, to, promotion })
+    }
+}
+
+impl Position {
+    pub fn parse_uci(&self, text: &str) -> Option<Move> {
+        let mv = Move::from_uci(text)?;
+        if self.is_legal(mv) {
+            Some(mv)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uci_round_trip() {
+        let position = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+
+        for mv in position.legal_moves() {
+            assert_eq!(position.parse_uci(&mv.to_uci()), Some(mv));
+        }
+        assert_eq!(Move::from_uci("e7e8q").map(Move::to_uci), Some("e7e8q".to_string()));
+        assert_eq!(Move::from_uci("e7e8x"), None);
+        assert_eq!(Move::from_uci("e2"), None);
+        // Well formed, but not legal here
+        assert_eq!(position.parse_uci("e2e5"), None);
+    }
+}
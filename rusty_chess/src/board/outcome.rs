@@ -0,0 +1,200 @@
+use std::fmt;
+
+use crate::board::bitboard::squares;
+use crate::board::piece::{Owner, PieceType};
+use crate::board::position::Position;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    Checkmate { winner: Owner },
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    // The side that ran out of time loses, unless the other side couldn't ever mate
+    Timeout { winner: Owner },
+    TimeoutVsInsufficientMaterial,
+    Resignation { winner: Owner },
+    DrawAgreed,
+}
+
+impl GameResult {
+    pub fn winner(self) -> Option<Owner> {
+        match self {
+            GameResult::Checkmate { winner } | GameResult::Timeout { winner } | GameResult::Resignation { winner } => Some(winner),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::Checkmate { winner: Owner::White } => write!(f, "Checkmate, white wins"),
+            GameResult::Checkmate { winner: Owner::Black } => write!(f, "Checkmate, black wins"),
+            GameResult::Stalemate => write!(f, "Draw by stalemate"),
+            GameResult::ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+            GameResult::FiftyMoveRule => write!(f, "Draw by the fifty move rule"),
+            GameResult::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+            GameResult::Timeout { winner: Owner::White } => write!(f, "White wins on time"),
+            GameResult::Timeout { winner: Owner::Black } => write!(f, "Black wins on time"),
+            GameResult::TimeoutVsInsufficientMaterial => write!(f, "Draw on time, mate impossible"),
+            GameResult::Resignation { winner: Owner::White } => write!(f, "Black resigns, white wins"),
+            GameResult::Resignation { winner: Owner::Black } => write!(f, "White resigns, black wins"),
+            GameResult::DrawAgreed => write!(f, "Draw by agreement"),
+        }
+    }
+}
+
+impl Position {
+
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check() && self.legal_moves().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check() && self.legal_moves().is_empty()
+    }
+
+    // Neither side has enough left to ever give mate: bare kings, a single
+    // minor piece, or only bishops that all stand on the same colour
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut bishop_colours = [false; 2];
+        for owner in [Owner::Black, Owner::White] {
+            for piece_type in [PieceType::Pawn, PieceType::Rook, PieceType::Queen] {
+                if self.bitboard(owner, piece_type) != 0 {
+                    return false;
+                }
+            }
+            for square in squares(self.bitboard(owner, PieceType::Bishop)) {
+                bishop_colours[(square.col() + square.row()) % 2] = true;
+            }
+        }
+
+        let knights = self.bitboard(Owner::Black, PieceType::Knight) | self.bitboard(Owner::White, PieceType::Knight);
+        let bishops = self.bitboard(Owner::Black, PieceType::Bishop) | self.bitboard(Owner::White, PieceType::Bishop);
+        let minors = (knights | bishops).count_ones();
+        let only_bishops = knights == 0;
+
+        minors <= 1 || (only_bishops && !(bishop_colours[0] && bishop_colours[1]))
+    }
+
+    // Whether `owner` has anything left that could mate with help from the
+    // other side: a pawn, rook or queen, or at least two minor pieces
+    pub fn can_checkmate(&self, owner: Owner) -> bool {
+        let minors = self.bitboard(owner, PieceType::Knight) | self.bitboard(owner, PieceType::Bishop);
+        [PieceType::Pawn, PieceType::Rook, PieceType::Queen].iter().any(|&piece_type| self.bitboard(owner, piece_type) != 0)
+            || minors.count_ones() >= 2
+    }
+
+    // The result when `flagged` runs out of time in this position
+    pub fn timeout(&self, flagged: Owner) -> GameResult {
+        let winner = flagged.opponent();
+        if self.can_checkmate(winner) {
+            GameResult::Timeout { winner }
+        } else {
+            GameResult::TimeoutVsInsufficientMaterial
+        }
+    }
+
+    // The hash decides whether positions are the same. Its en passant file
+    // only counts when a pawn could actually take on it, pins included.
+    fn repetition_key(&self) -> u64 {
+        let en_passant_key = self.en_passant_key();
+        if en_passant_key == 0 {
+            return self.hash;
+        }
+
+        let can_capture = self
+            .legal_moves()
+            .iter()
+            .any(|mv| Some(mv.to) == self.en_passant && self.piece_at(mv.from).map(|p| p.piece_type) == Some(PieceType::Pawn));
+        if can_capture {
+            self.hash
+        } else {
+            self.hash ^ en_passant_key
+        }
+    }
+
+    // How often this position has come up in the game, counting itself.
+    // `history` holds the positions before each move played so far.
+    pub fn repetitions(&self, history: &[Position]) -> usize {
+        let key = self.repetition_key();
+        1 + history.iter().filter(|p| p.repetition_key() == key).count()
+    }
+
+    pub fn outcome(&self, history: &[Position]) -> Option<GameResult> {
+        if self.legal_moves().is_empty() {
+            return if self.in_check() {
+                Some(GameResult::Checkmate { winner: self.turn.opponent() })
+            } else {
+                Some(GameResult::Stalemate)
+            };
+        }
+        if self.has_insufficient_material() {
+            return Some(GameResult::InsufficientMaterial);
+        }
+        if self.halfmove_clock >= 100 {
+            return Some(GameResult::FiftyMoveRule);
+        }
+        if self.repetitions(history) >= 3 {
+            return Some(GameResult::ThreefoldRepetition);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::piece::Piece;
+    use crate::board::square::Square;
+
+    #[test]
+    fn test_fools_mate() {
+        let mut position = Position::default();
+        for san in ["f3", "e5", "g4", "Qh4"] {
+            position.apply_move(position.parse_san(san).unwrap());
+        }
+
+        assert!(position.in_check());
+        assert_eq!(position.outcome(&[]), Some(GameResult::Checkmate { winner: Owner::Black }));
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        let mut position = Position::default();
+        let mut history = Vec::new();
+        let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8"];
+
+        for san in shuffle.iter().chain(shuffle.iter()) {
+            assert_eq!(position.outcome(&history), None);
+            history.push(position);
+            position.apply_move(position.parse_san(san).unwrap());
+        }
+
+        assert_eq!(position.outcome(&history), Some(GameResult::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let mut position = Position::empty();
+        position.set_piece(Square::new(4, 7), Some(Piece::new(Owner::White, PieceType::King)));
+        position.set_piece(Square::new(4, 0), Some(Piece::new(Owner::Black, PieceType::King)));
+        position.set_piece(Square::new(2, 7), Some(Piece::new(Owner::White, PieceType::Bishop)));
+        assert!(position.has_insufficient_material());
+
+        position.set_piece(Square::new(2, 0), Some(Piece::new(Owner::Black, PieceType::Bishop)));
+        assert!(!position.has_insufficient_material());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/4P3/4K2N w - - 0 1").unwrap();
+
+        assert_eq!(position.timeout(Owner::Black), GameResult::Timeout { winner: Owner::White });
+        // Black's lone king can't mate, so running out of time only draws
+        assert_eq!(position.timeout(Owner::White), GameResult::TimeoutVsInsufficientMaterial);
+        assert_eq!(GameResult::Timeout { winner: Owner::White }.to_pgn(), "1-0");
+    }
+}
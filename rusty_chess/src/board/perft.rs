@@ -0,0 +1,101 @@
+use crate::board::movegen::Move;
+use crate::board::position::Position;
+
+impl Position {
+
+    // Number of move paths `depth` half moves long from this position. Comparing
+    // this against published numbers is the surest check of the move generator.
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.legal_moves();
+        // Every legal move is one path, no need to play them out
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        moves
+            .into_iter()
+            .map(|mv| {
+                let mut child = *self;
+                child.apply_move(mv);
+                child.perft(depth - 1)
+            })
+            .sum()
+    }
+
+    // Perft split up by the first move, for narrowing down where a count goes wrong
+    pub fn perft_divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        self.legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut child = *self;
+                child.apply_move(mv);
+                (mv, child.perft(depth.saturating_sub(1)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::square::Square;
+
+    // Reference positions and counts from the Chess Programming Wiki's perft results page
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    // Rook and pawn ending full of en passant captures and discovered checks
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    // Promotions, with and without capture, and castling rights for black only
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+
+    fn assert_perft(fen: &str, counts: &[u64]) {
+        let position = Position::from_fen(fen).unwrap();
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(position.perft(depth as u32 + 1), count, "{} at depth {}", fen, depth + 1);
+        }
+    }
+
+    #[test]
+    fn test_perft_start_position() {
+        assert_perft(crate::board::fen::STARTING_FEN, &[20, 400, 8_902, 197_281]);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        assert_perft(KIWIPETE, &[48, 2_039, 97_862]);
+    }
+
+    #[test]
+    fn test_perft_en_passant() {
+        assert_perft(POSITION_3, &[14, 191, 2_812, 43_238]);
+    }
+
+    #[test]
+    fn test_perft_promotions() {
+        assert_perft(POSITION_4, &[6, 264, 9_467]);
+        assert_perft(POSITION_5, &[44, 1_486, 62_379]);
+    }
+
+    // Too slow for every run, try `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_perft_deep() {
+        assert_eq!(Position::default().perft(5), 4_865_609);
+        assert_eq!(Position::from_fen(KIWIPETE).unwrap().perft(4), 4_085_603);
+        assert_eq!(Position::from_fen(POSITION_3).unwrap().perft(5), 674_624);
+        assert_eq!(Position::from_fen(POSITION_4).unwrap().perft(4), 422_333);
+        assert_eq!(Position::from_fen(POSITION_5).unwrap().perft(4), 2_103_487);
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let divide = Position::default().perft_divide(3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), 8_902);
+        assert!(divide.contains(&(Move::new(Square::new(4, 6), Square::new(4, 4)), 600)));
+    }
+}
@@ -0,0 +1,352 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::board::fen::{FenError, STARTING_FEN};
+use crate::board::movegen::Move;
+use crate::board::outcome::GameResult;
+use crate::board::piece::Owner;
+use crate::board::position::Position;
+use crate::board::san::SanError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    InvalidTag(String),
+    InvalidFen(FenError),
+    // `ply` counts half moves from the start of the game, starting at 1
+    InvalidMove { ply: usize, error: SanError },
+    UnterminatedComment,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag pair {}", tag),
+            PgnError::InvalidFen(e) => write!(f, "invalid FEN tag: {}", e),
+            PgnError::InvalidMove { ply, error } => write!(f, "half move {}: {}", ply, error),
+            PgnError::UnterminatedComment => write!(f, "comment or variation is never closed"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl GameResult {
+    // Result token used in PGN tags and at the end of the move text
+    pub fn to_pgn(self) -> &'static str {
+        match self.winner() {
+            Some(Owner::White) => "1-0",
+            Some(Owner::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+// A recorded game: its tag pairs, the position it started from and the moves played
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pgn {
+    pub tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+}
+
+impl Pgn {
+
+    // The seven tags every PGN export should have, filled in for a game played today
+    pub fn new(white: &str, black: &str) -> Pgn {
+        Pgn {
+            tags: vec![
+                ("Event".to_string(), "Rusty Chess game".to_string()),
+                ("Site".to_string(), "?".to_string()),
+                ("Date".to_string(), today()),
+                ("Round".to_string(), "-".to_string()),
+                ("White".to_string(), white.to_string()),
+                ("Black".to_string(), black.to_string()),
+                ("Result".to_string(), "*".to_string()),
+            ],
+            start: Position::default(),
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // Every position in the game, from the start to after the last move
+    pub fn positions(&self) -> Vec<Position> {
+        let mut position = self.start;
+        let mut positions = vec![position];
+        for &mv in &self.moves {
+            position.apply_move(mv);
+            positions.push(position);
+        }
+        positions
+    }
+
+    // Reads the first game in `text`. Comments, variations and NAGs are skipped.
+    pub fn parse(text: &str) -> Result<Pgn, PgnError> {
+        let mut pgn = Pgn {
+            tags: Vec::new(),
+            start: Position::default(),
+            moves: Vec::new(),
+        };
+
+        let mut lines = text.lines().map(str::trim).peekable();
+        while let Some(line) = lines.peek() {
+            if line.is_empty() {
+                lines.next();
+                continue;
+            }
+            if !line.starts_with('[') {
+                break;
+            }
+            let (name, value) = parse_tag(line)?;
+            pgn.tags.push((name, value));
+            lines.next();
+        }
+
+        if let Some(fen) = pgn.tag("FEN") {
+            pgn.start = Position::from_fen(fen).map_err(PgnError::InvalidFen)?;
+        }
+
+        // The move text runs until the next game's tags start
+        let movetext: Vec<&str> = lines.take_while(|line| !line.starts_with('[')).collect();
+        let mut position = pgn.start;
+        for token in movetext_tokens(&movetext.join("\n"))? {
+            let mv = position
+                .parse_san(&token)
+                .map_err(|error| PgnError::InvalidMove { ply: pgn.moves.len() + 1, error })?;
+            position.apply_move(mv);
+            pgn.moves.push(mv);
+        }
+
+        Ok(pgn)
+    }
+}
+
+impl fmt::Display for Pgn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        // Games that don't start from the usual setup need to say where they do
+        if self.start.to_fen() != STARTING_FEN && self.tag("FEN").is_none() {
+            writeln!(f, "[SetUp \"1\"]")?;
+            writeln!(f, "[FEN \"{}\"]", self.start.to_fen())?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = movetext(&self.start, &self.moves);
+        tokens.push(self.tag("Result").unwrap_or("*").to_string());
+
+        // Keep lines under 80 characters like most exporters do
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + token.len() + 1 > 79 {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+// The moves in SAN with move numbers, e.g. "1." "e4" "e5" or "12..." "Nf6"
+// when the line starts with black to move
+pub fn movetext(start: &Position, moves: &[Move]) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut position = *start;
+    for (i, &mv) in moves.iter().enumerate() {
+        if position.turn == Owner::White {
+            tokens.push(format!("{}.", position.fullmove_number));
+        } else if i == 0 {
+            tokens.push(format!("{}...", position.fullmove_number));
+        }
+        tokens.push(position.to_san(mv));
+        position.apply_move(mv);
+    }
+    tokens
+}
+
+// `[Name "Value"]`, where the value may contain escaped quotes and backslashes
+fn parse_tag(line: &str) -> Result<(String, String), PgnError> {
+    let invalid = || PgnError::InvalidTag(line.to_string());
+
+    let inner = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')).ok_or_else(invalid)?;
+    let (name, rest) = inner.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let quoted = rest.trim().strip_prefix('"').and_then(|r| r.strip_suffix('"')).ok_or_else(invalid)?;
+
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            value.push(chars.next().ok_or_else(invalid)?);
+        } else {
+            value.push(c);
+        }
+    }
+
+    Ok((name.to_string(), value))
+}
+
+// The SAN moves of the main line, without move numbers or the result
+fn movetext_tokens(movetext: &str) -> Result<Vec<String>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = movetext.chars().peekable();
+    let mut word = String::new();
+    let mut variation_depth = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                // Comments don't nest
+                if !chars.by_ref().any(|c| c == '}') {
+                    return Err(PgnError::UnterminatedComment);
+                }
+            }
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => variation_depth += 1,
+            ')' => variation_depth -= 1,
+            c if c.is_whitespace() => {}
+            _ => {
+                word.push(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{};()".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                if variation_depth == 0 {
+                    if let Some(san) = movetext_move(&word) {
+                        tokens.push(san);
+                    }
+                }
+                word.clear();
+            }
+        }
+    }
+
+    if variation_depth != 0 {
+        return Err(PgnError::UnterminatedComment);
+    }
+    Ok(tokens)
+}
+
+// Strip the move number from a word like "12.Nf3" or "12...", and drop
+// words that aren't moves at all (NAGs and results)
+fn movetext_move(word: &str) -> Option<String> {
+    if word.starts_with('$') || ["1-0", "0-1", "1/2-1/2", "*"].contains(&word) {
+        return None;
+    }
+
+    let san = match word.rfind('.') {
+        Some(dot) if word[..dot].trim_end_matches('.').chars().all(|c| c.is_ascii_digit()) => &word[dot + 1..],
+        _ => word,
+    };
+    // A move number may also be written without its dot
+    if san.is_empty() || san.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(san.to_string())
+}
+
+// Today's date as "YYYY.MM.DD", worked out from the system clock in UTC
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHOLARS_MATE: &str = "[Event \"Casual \\\"blitz\\\"\"]
+[White \"Alice\"]
+[Black \"Bob\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Bc4 {attacking f7} Nc6 (2... Nf6 3. d3) 3. Qh5 $2 Nf6?? 4. Qxf7# 1-0
+";
+
+    #[test]
+    fn test_parse() {
+        let pgn = Pgn::parse(SCHOLARS_MATE).unwrap();
+
+        assert_eq!(pgn.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(pgn.tag("White"), Some("Alice"));
+        assert_eq!(pgn.moves.len(), 7);
+        assert!(pgn.positions().last().unwrap().is_checkmate());
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let pgn = Pgn::parse(SCHOLARS_MATE).unwrap();
+        let exported = pgn.to_string();
+
+        assert!(exported.contains("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0"));
+        assert_eq!(Pgn::parse(&exported).unwrap(), pgn);
+    }
+
+    #[test]
+    fn test_export_from_fen() {
+        let mut pgn = Pgn::new("White", "Black");
+        pgn.start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 30").unwrap();
+        pgn.moves.push(pgn.start.parse_san("Kd7").unwrap());
+        let exported = pgn.to_string();
+
+        assert!(exported.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 30\"]"));
+        assert!(exported.contains("30... Kd7 *"));
+        assert_eq!(Pgn::parse(&exported).unwrap().moves, pgn.moves);
+    }
+
+    #[test]
+    fn test_movetext() {
+        let start = Position::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let moves = [Move::from_uci("f1b5").unwrap(), Move::from_uci("a7a6").unwrap()];
+        assert_eq!(movetext(&start, &moves).join(" "), "3. Bb5 a6");
+
+        let mut after = start;
+        after.apply_move(moves[0]);
+        assert_eq!(movetext(&after, &moves[1..]).join(" "), "3... a6");
+    }
+
+    #[test]
+    fn test_invalid_move() {
+        let error = Pgn::parse("1. e4 e4").unwrap_err();
+        assert_eq!(error, PgnError::InvalidMove { ply: 2, error: SanError::Illegal("e4".to_string()) });
+    }
+}
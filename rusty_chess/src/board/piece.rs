@@ -1,14 +1,3 @@
-use std::ops::Neg;
-
-use raylib::prelude::*;
-use crate::game::Game;
-use crate::board::space::{Space};
-
-enum Movement {
-    Diagonal {max_steps: u8},
-    Straight {max_steps: u8},
-}
-
 #[derive(Debug, Eq, Hash, Copy, Clone, PartialEq)]
 pub enum PieceType {
     Pawn, 
@@ -19,133 +8,123 @@ pub enum PieceType {
     King 
 }
 
-impl PieceType {
+const STRAIGHT: [(i8, i8); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+const DIAGONAL: [(i8, i8); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+pub(crate) const ALL_DIRECTIONS: [(i8, i8); 8] = [(0, -1), (0, 1), (-1, 0), (1, 0), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+pub(crate) const KNIGHT_JUMPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
 
-    fn gen_movement_vec(self, g: &Game, piece_struct: &Piece) -> Vec<Movement> {
+impl PieceType {
 
-        let movement_vec = match piece_struct.piece_type {
-            // Pawn can move 1 or 2 spaces, forward or diagonal
-            PieceType::Pawn => vec![Movement::Straight{max_steps:2}, Movement::Diagonal{max_steps:1}],
+    // In discriminant order, which is also the order of `Position::pieces`
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Rook,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+
+    // Directions the piece moves in as (column, row) steps, and whether it
+    // keeps sliding along them until it is blocked. Pawns move differently
+    // depending on their owner and whether they capture, so they have none.
+    pub fn movement(self) -> (&'static [(i8, i8)], bool) {
+        match self {
+            PieceType::Pawn => (&[], false),
 
             // Rook can move any number of spaces in a straight line
-            PieceType::Rook => vec![Movement::Straight{max_steps:8}],
+            PieceType::Rook => (&STRAIGHT, true),
 
-            // Knight can move 2 spaces and then 3 spaces in straight lines
-            PieceType::Knight => vec![Movement::Straight{max_steps:2}, Movement::Straight{max_steps:3}],
+            // Knight jumps two spaces one way and one space the other
+            PieceType::Knight => (&KNIGHT_JUMPS, false),
 
             // Bishop can move any number of spaces diagonally
-            PieceType::Bishop => vec![Movement::Diagonal{max_steps:8}],
+            PieceType::Bishop => (&DIAGONAL, true),
 
             // Queen can move any number of spaces in any direction
-            PieceType::Queen => vec![Movement::Straight{max_steps:8}, Movement::Diagonal{max_steps:8}],
+            PieceType::Queen => (&ALL_DIRECTIONS, true),
 
             // King can move one space in any direction
-            PieceType::King => vec![Movement::Straight{max_steps:1}, Movement::Diagonal{max_steps:1}],
-        };
+            PieceType::King => (&ALL_DIRECTIONS, false),
+        }
+    }
 
-        movement_vec
+    // Upper case letter used for the piece in FEN and algebraic notation
+    pub fn to_char(self) -> char {
+        match self {
+            PieceType::Pawn => 'P',
+            PieceType::Rook => 'R',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Queen => 'Q',
+            PieceType::King => 'K',
+        }
     }
 
-}
+    pub fn from_char(c: char) -> Option<PieceType> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(PieceType::Pawn),
+            'R' => Some(PieceType::Rook),
+            'N' => Some(PieceType::Knight),
+            'B' => Some(PieceType::Bishop),
+            'Q' => Some(PieceType::Queen),
+            'K' => Some(PieceType::King),
+            _ => None,
+        }
+    }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Piece {
-    pub rect: Rectangle,
-    pub owner: u8,
-    pub piece_type: PieceType,
-    pub origin: (u8, u8),
 }
 
+// Black sets up on rows 0 and 1 (the top of the board), white on rows 6 and 7.
+// The discriminants double as the index into the per-type texture vectors.
+#[derive(Debug, Eq, Hash, Copy, Clone, PartialEq)]
+pub enum Owner {
+    Black,
+    White
+}
 
-impl Piece {
-    fn within_bounds(self, g: &Game, coords: (i8, i8)) -> bool {
-        let rank = coords.0;
-        let file = coords.1;
-
-        if rank < 8 && rank >= 0 {
-            if file < 8 && rank >= 0 {
-                return true;
-            }
+impl Owner {
+    pub fn opponent(self) -> Owner {
+        match self {
+            Owner::Black => Owner::White,
+            Owner::White => Owner::Black,
         }
-        false
     }
 
-    pub fn draw_straight(self, g: &Game, d: &mut RaylibDrawHandle, coords: (i8, i8)) {
-        // North and south
-        for i in 0..8 {
-            d.draw_rectangle_rec(
-            g.layout[coords.0 as usize][i as usize].rect,
-                color::Color {r: 255, g: 0, b: 0, a: 150 });
-            d.draw_rectangle_rec(
-            g.layout[i as usize][coords.1 as usize].rect,
-                color::Color {r: 255, g: 0, b: 0, a: 150 });
+    // Row step of this side's pawns
+    pub fn forward(self) -> i8 {
+        match self {
+            Owner::Black => 1,
+            Owner::White => -1,
         }
     }
+}
 
-    pub fn draw_diagonal(self, g: &Game, d: &mut RaylibDrawHandle, coords: (i8, i8)) {
-        // Max amount of spaces will always be 8
-        let file = coords.0;
-        let rank = coords.1;
-
-        let dx = (file - 7).abs();
-        let dy = (rank - 7).abs();
-        
-        for i in 0..8 {
-            if i + rank < 8 && i + file < 8 {
-                println!("North east");
-                d.draw_rectangle_rec(
-                g.layout[(file + i) as usize][(rank + i) as usize].rect,
-                color::Color {r: 255, g: 0, b: 0, a: 150 });
-            }
-            if rank - i >= 0 && file - i >= 0 {
-                d.draw_rectangle_rec(
-                g.layout[(file - i) as usize][(rank - i) as usize].rect,
-                color::Color {r: 255, g: 0, b: 0, a: 150 });
-            }
-            if i + rank < 8 && file - i >= 0 {
-                d.draw_rectangle_rec(
-                g.layout[(rank + i) as usize][(file - i) as usize].rect,
-                color::Color {r: 255, g: 0, b: 0, a: 150 });
-            }
-            if rank - i >= 0 && i + file < 8 {
-                d.draw_rectangle_rec(
-                g.layout[(rank - i) as usize][(file + i) as usize].rect,
-                color::Color {r: 255, g: 0, b: 0, a: 150 });
-            }
-        }
+#[derive(Debug, Eq, Hash, Copy, Clone, PartialEq)]
+pub struct Piece {
+    pub owner: Owner,
+    pub piece_type: PieceType,
+}
 
-    }
 
-    pub fn draw_valid_moves(self, g: &Game, d: &mut RaylibDrawHandle) {
-        for i in self.origin.0..8 {
-            match self.piece_type {
-                PieceType::Bishop => {
-                    d.draw_rectangle_rec(
-                        g.layout[self.origin.0 as usize][self.origin.1 as usize].rect,
-                        color::Color {r: 255, g: 0, b: 0, a: 255 });
-                },
-                _ => todo!(),
-            }
+impl Piece {
+    pub fn new(owner: Owner, piece_type: PieceType) -> Piece {
+        Piece {
+            owner,
+            piece_type,
         }
     }
-    pub fn piece_to_name(index: u8) -> PieceType {
-        match index {
-            1 => PieceType::Pawn,
-            2 => PieceType::Rook,
-            3 => PieceType::Knight,
-            4 => PieceType::Bishop,
-            5 => PieceType::Queen,
-            6 => PieceType::King,
-            _ => PieceType::Pawn,
+
+    // FEN letter: upper case for white, lower case for black
+    pub fn to_fen_char(self) -> char {
+        match self.owner {
+            Owner::White => self.piece_type.to_char(),
+            Owner::Black => self.piece_type.to_char().to_ascii_lowercase(),
         }
     }
 
-    pub fn new(space: Space, owner: u8, piece_type: u8) -> Piece {
-        Piece {
-            rect: space.rect,
-            owner,
-            piece_type: Self::piece_to_name(piece_type),
-            origin: (space.rect.x as u8, space.rect.y as u8),
-        }
+    pub fn from_fen_char(c: char) -> Option<Piece> {
+        let owner = if c.is_ascii_uppercase() { Owner::White } else { Owner::Black };
+        PieceType::from_char(c).map(|piece_type| Piece::new(owner, piece_type))
     }
 }
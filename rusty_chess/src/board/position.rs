@@ -0,0 +1,143 @@
+use crate::board::bitboard::Bitboard;
+use crate::board::fen::STARTING_FEN;
+use crate::board::piece::{Owner, Piece, PieceType};
+use crate::board::square::Square;
+use crate::board::zobrist::piece_key;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        }
+    }
+
+    pub fn none() -> CastlingRights {
+        CastlingRights {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+        }
+    }
+}
+
+// The full state of a chess game, without anything needed to draw it.
+//
+// Pieces are kept as bitboards, one per owner and piece type and indexed by
+// their discriminants, e.g. `pieces[Owner::White as usize][PieceType::Rook as usize]`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub pieces: [[Bitboard; 6]; 2],
+    pub turn: Owner,
+    pub castling: CastlingRights,
+    // Square a pawn skipped over with a double push on the last move
+    pub en_passant: Option<Square>,
+    // Half moves since the last capture or pawn move
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    // Zobrist hash, kept up to date by `set_piece` and `apply_move`. Call
+    // `refresh_hash` after changing the other fields directly.
+    pub hash: u64,
+}
+
+impl Position {
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        let bit = square.bit();
+        if self.occupied() & bit == 0 {
+            return None;
+        }
+
+        for owner in [Owner::Black, Owner::White] {
+            for piece_type in PieceType::ALL {
+                if self.pieces[owner as usize][piece_type as usize] & bit != 0 {
+                    return Some(Piece::new(owner, piece_type));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        if let Some(old) = self.piece_at(square) {
+            self.pieces[old.owner as usize][old.piece_type as usize] &= !square.bit();
+            self.hash ^= piece_key(old, square);
+        }
+        if let Some(piece) = piece {
+            self.pieces[piece.owner as usize][piece.piece_type as usize] |= square.bit();
+            self.hash ^= piece_key(piece, square);
+        }
+    }
+
+    pub fn refresh_hash(&mut self) {
+        self.hash = self.zobrist_hash();
+    }
+
+    pub fn bitboard(&self, owner: Owner, piece_type: PieceType) -> Bitboard {
+        self.pieces[owner as usize][piece_type as usize]
+    }
+
+    pub fn occupied_by(&self, owner: Owner) -> Bitboard {
+        self.pieces[owner as usize].iter().fold(0, |all, board| all | board)
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.occupied_by(Owner::White) | self.occupied_by(Owner::Black)
+    }
+
+    pub fn empty() -> Position {
+        Position {
+            pieces: [[0; 6]; 2],
+            turn: Owner::White,
+            castling: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position::from_fen(STARTING_FEN).expect("starting position is valid FEN")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_position() {
+        let position = Position::default();
+
+        assert_eq!(position.turn, Owner::White);
+        assert_eq!(position.piece_at(Square::new(4, 7)), Some(Piece::new(Owner::White, PieceType::King)));
+        assert_eq!(position.piece_at(Square::new(3, 0)), Some(Piece::new(Owner::Black, PieceType::Queen)));
+        assert_eq!(position.piece_at(Square::new(4, 4)), None);
+        assert_eq!(position.occupied().count_ones(), 32);
+        assert_eq!(position.bitboard(Owner::Black, PieceType::Pawn), 0xff00);
+    }
+
+    #[test]
+    fn test_set_piece_replaces() {
+        let mut position = Position::default();
+        let e2 = Square::new(4, 6);
+        position.set_piece(e2, Some(Piece::new(Owner::Black, PieceType::Queen)));
+
+        assert_eq!(position.piece_at(e2), Some(Piece::new(Owner::Black, PieceType::Queen)));
+        assert_eq!(position.bitboard(Owner::White, PieceType::Pawn) & e2.bit(), 0);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+
+use crate::board::movegen::Move;
+use crate::board::piece::PieceType;
+use crate::board::position::Position;
+use crate::board::square::Square;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    Invalid(String),
+    Illegal(String),
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "'{}' is not a move in algebraic notation", san),
+            SanError::Illegal(san) => write!(f, "'{}' is not a legal move", san),
+            SanError::Ambiguous(san) => write!(f, "'{}' could be more than one move", san),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Position {
+
+    // Standard Algebraic Notation for a legal move, e.g. "Nbd7", "exd6", "O-O" or "e8=Q+"
+    pub fn to_san(&self, mv: Move) -> String {
+        let piece = match self.piece_at(mv.from) {
+            Some(p) => p,
+            None => return String::new(),
+        };
+
+        let mut san = String::new();
+        if piece.piece_type == PieceType::King && mv.from.col().abs_diff(mv.to.col()) == 2 {
+            san.push_str(if mv.to.col() > mv.from.col() { "O-O" } else { "O-O-O" });
+        } else {
+            let capture = self.is_capture(mv);
+
+            if piece.piece_type == PieceType::Pawn {
+                if capture {
+                    san.push((b'a' + mv.from.col() as u8) as char);
+                }
+            } else {
+                san.push(piece.piece_type.to_char());
+                san.push_str(&self.disambiguation(mv));
+            }
+
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push(promotion.to_char());
+            }
+        }
+
+        let mut after = *self;
+        after.apply_move(mv);
+        if after.is_checkmate() {
+            san.push('#');
+        } else if after.in_check() {
+            san.push('+');
+        }
+
+        san
+    }
+
+    // Just enough of the starting space to tell the move apart from other
+    // pieces of the same type that could reach the same space
+    fn disambiguation(&self, mv: Move) -> String {
+        let piece_type = self.piece_at(mv.from).map(|p| p.piece_type);
+        let rivals: Vec<Square> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|other| other.to == mv.to && other.from != mv.from)
+            .filter(|other| self.piece_at(other.from).map(|p| p.piece_type) == piece_type)
+            .map(|other| other.from)
+            .collect();
+
+        let from = mv.from.to_string();
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|rival| rival.col() != mv.from.col()) {
+            from[..1].to_string()
+        } else if rivals.iter().all(|rival| rival.row() != mv.from.row()) {
+            from[1..].to_string()
+        } else {
+            from
+        }
+    }
+
+    // Read a move in SAN. Check marks, annotations like "!?" and missing or
+    // extra capture marks are tolerated, as they are in a lot of real PGN files.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let text = san.trim_end_matches(['+', '#', '!', '?']);
+
+        if text == "O-O" || text == "0-0" || text == "O-O-O" || text == "0-0-0" {
+            let king_side = text.len() == 3;
+            return self
+                .legal_moves()
+                .into_iter()
+                .find(|mv| {
+                    self.piece_at(mv.from).map(|p| p.piece_type) == Some(PieceType::King)
+                        && mv.from.col().abs_diff(mv.to.col()) == 2
+                        && (mv.to.col() > mv.from.col()) == king_side
+                })
+                .ok_or(SanError::Illegal(san.to_string()));
+        }
+
+        let mut chars: Vec<char> = text.chars().filter(|&c| c != 'x' && c != '-' && c != ':').collect();
+
+        // A trailing piece letter, with or without '=', is the promotion
+        let mut promotion = None;
+        if let Some(&last) = chars.last() {
+            if last.is_ascii_uppercase() {
+                promotion = Some(PieceType::from_char(last).ok_or(SanError::Invalid(san.to_string()))?);
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        let piece_type = match chars.first() {
+            Some(&c) if c.is_ascii_uppercase() => {
+                chars.remove(0);
+                PieceType::from_char(c).ok_or(SanError::Invalid(san.to_string()))?
+            }
+            _ => PieceType::Pawn,
+        };
+
+        if chars.len() < 2 {
+            return Err(SanError::Invalid(san.to_string()));
+        }
+        let target: String = chars[chars.len() - 2..].iter().collect();
+        let to = Square::parse(&target).ok_or(SanError::Invalid(san.to_string()))?;
+
+        // Whatever is left over narrows down the starting file and/or rank
+        let mut from_col = None;
+        let mut from_row = None;
+        for &c in &chars[..chars.len() - 2] {
+            match c {
+                'a'..='h' => from_col = Some(c as usize - 'a' as usize),
+                '1'..='8' => from_row = Some(8 - c.to_digit(10).unwrap() as usize),
+                _ => return Err(SanError::Invalid(san.to_string())),
+            }
+        }
+
+        let candidates: Vec<Move> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.to == to && mv.promotion == promotion)
+            .filter(|mv| self.piece_at(mv.from).map(|p| p.piece_type) == Some(piece_type))
+            .filter(|mv| from_col.is_none_or(|col| mv.from.col() == col))
+            .filter(|mv| from_row.is_none_or(|row| mv.from.row() == row))
+            .collect();
+
+        match candidates.len() {
+            0 => Err(SanError::Illegal(san.to_string())),
+            1 => Ok(candidates[0]),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_san_round_trip() {
+        let position = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+
+        for mv in position.legal_moves() {
+            assert_eq!(position.parse_san(&position.to_san(mv)), Ok(mv));
+        }
+        assert_eq!(position.to_san(Move::new(Square::new(4, 7), Square::new(6, 7))), "O-O");
+        assert_eq!(position.to_san(Move::new(Square::new(4, 3), Square::new(5, 1))), "Nxf7");
+        assert_eq!(position.to_san(Move::new(Square::new(3, 3), Square::new(4, 2))), "dxe6");
+    }
+
+    #[test]
+    fn test_disambiguation_and_promotion() {
+        let position = Position::from_fen("k7/6P1/8/8/8/7K/8/R6R w - - 0 1").unwrap();
+
+        assert_eq!(position.to_san(Move::new(Square::new(0, 7), Square::new(3, 7))), "Rad1");
+        assert_eq!(position.to_san(Move::with_promotion(Square::new(6, 1), Square::new(6, 0), PieceType::Queen)), "g8=Q+");
+        assert_eq!(position.parse_san("g8Q"), Ok(Move::with_promotion(Square::new(6, 1), Square::new(6, 0), PieceType::Queen)));
+        assert_eq!(position.parse_san("Rd1"), Err(SanError::Ambiguous("Rd1".to_string())));
+    }
+}
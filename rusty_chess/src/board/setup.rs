@@ -0,0 +1,130 @@
+use std::fmt;
+
+use crate::board::bitboard::squares;
+use crate::board::piece::{Owner, Piece, PieceType};
+use crate::board::position::Position;
+use crate::board::square::Square;
+
+// Why a position set up by hand can't be played from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SetupError {
+    // Each side needs exactly one king
+    KingCount { owner: Owner, count: u32 },
+    PawnOnBackRank(Square),
+    // The side that just moved can't have left its king in check
+    OpponentInCheck,
+    // A right to castle without the king and that rook on their starting squares
+    InvalidCastling,
+    // An en passant square no pawn of the side that just moved could have skipped
+    InvalidEnPassant(Square),
+}
+
+fn side_name(owner: Owner) -> &'static str {
+    match owner {
+        Owner::White => "white",
+        Owner::Black => "black",
+    }
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetupError::KingCount { owner, count } => write!(f, "{} has {} kings, there must be one", side_name(*owner), count),
+            SetupError::PawnOnBackRank(square) => write!(f, "the pawn on {} can't be on the first or last rank", square),
+            SetupError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            SetupError::InvalidCastling => write!(f, "castling needs the king and rook on their starting squares"),
+            SetupError::InvalidEnPassant(square) => write!(f, "no pawn can have just passed {} for en passant", square),
+        }
+    }
+}
+
+impl std::error::Error for SetupError {}
+
+impl Position {
+    // Whether the side that just moved could have pushed a pawn two squares
+    // past `square`: it is on the third rank from that side, empty, and the
+    // pawn stands in front of it with nothing left behind
+    pub fn en_passant_fits(&self, square: Square) -> bool {
+        let (row, pawn_row, start_row) = match self.turn {
+            Owner::White => (2, 3, 1),
+            Owner::Black => (5, 4, 6),
+        };
+        let col = square.col();
+        square.row() == row
+            && self.piece_at(square).is_none()
+            && self.piece_at(Square::new(col, start_row)).is_none()
+            && self.piece_at(Square::new(col, pawn_row)) == Some(Piece::new(self.turn.opponent(), PieceType::Pawn))
+    }
+
+    // Whether the position could come up in a game, as far as can be told
+    // without knowing the moves that led to it
+    pub fn validate(&self) -> Result<(), SetupError> {
+        for owner in [Owner::White, Owner::Black] {
+            let count = self.bitboard(owner, PieceType::King).count_ones();
+            if count != 1 {
+                return Err(SetupError::KingCount { owner, count });
+            }
+        }
+
+        let pawns = self.bitboard(Owner::White, PieceType::Pawn) | self.bitboard(Owner::Black, PieceType::Pawn);
+        if let Some(square) = squares(pawns).find(|square| square.row() == 0 || square.row() == 7) {
+            return Err(SetupError::PawnOnBackRank(square));
+        }
+
+        let waiting = self.turn.opponent();
+        if self.king_square(waiting).is_some_and(|king| self.is_attacked(king, self.turn)) {
+            return Err(SetupError::OpponentInCheck);
+        }
+
+        let at = |col: usize, row: usize, owner: Owner, piece_type: PieceType| {
+            self.piece_at(Square::new(col, row)) == Some(Piece::new(owner, piece_type))
+        };
+        let rights = [
+            (self.castling.white_king_side, Owner::White, 7, 7),
+            (self.castling.white_queen_side, Owner::White, 0, 7),
+            (self.castling.black_king_side, Owner::Black, 7, 0),
+            (self.castling.black_queen_side, Owner::Black, 0, 0),
+        ];
+        for (allowed, owner, rook_col, row) in rights {
+            if allowed && !(at(4, row, owner, PieceType::King) && at(rook_col, row, owner, PieceType::Rook)) {
+                return Err(SetupError::InvalidCastling);
+            }
+        }
+
+        match self.en_passant {
+            Some(square) if !self.en_passant_fits(square) => Err(SetupError::InvalidEnPassant(square)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn validate(fen: &str) -> Result<(), SetupError> {
+        Position::from_fen(fen).unwrap().validate()
+    }
+
+    #[test]
+    fn test_valid_positions() {
+        assert_eq!(Position::default().validate(), Ok(()));
+        assert_eq!(validate("4k3/8/8/8/8/8/4P3/4K2R w K - 0 1"), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_positions() {
+        assert_eq!(validate("8/8/8/8/8/8/8/4K3 w - - 0 1"), Err(SetupError::KingCount { owner: Owner::Black, count: 0 }));
+        assert_eq!(validate("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"), Err(SetupError::KingCount { owner: Owner::White, count: 2 }));
+        assert_eq!(validate("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"), Err(SetupError::PawnOnBackRank(Square::new(7, 0))));
+        assert_eq!(validate("4k3/8/8/8/8/8/8/4K2R b - - 0 1"), Ok(()));
+        // White to move could just take the king
+        assert_eq!(validate("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"), Err(SetupError::OpponentInCheck));
+        assert_eq!(validate("4k3/8/8/8/8/8/8/4K1R1 w K - 0 1"), Err(SetupError::InvalidCastling));
+
+        // A position built by hand, not read from FEN, can still have a stray en passant square
+        let mut position = Position::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1").unwrap();
+        position.en_passant = Some(Square::new(4, 5));
+        assert_eq!(position.validate(), Err(SetupError::InvalidEnPassant(Square::new(4, 5))));
+    }
+}
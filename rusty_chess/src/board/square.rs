@@ -0,0 +1,97 @@
+use std::fmt;
+
+use crate::board::bitboard::Bitboard;
+
+// One of the 64 spaces of the board. They are numbered row by row from a8 (0)
+// to h1 (63), so like on the screen row 0 is black's back rank (rank 8).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    pub const fn new(col: usize, row: usize) -> Square {
+        Square((row * 8 + col) as u8)
+    }
+
+    pub const fn from_index(index: usize) -> Square {
+        Square(index as u8)
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub const fn col(self) -> usize {
+        self.0 as usize % 8
+    }
+
+    pub const fn row(self) -> usize {
+        self.0 as usize / 8
+    }
+
+    pub const fn bit(self) -> Bitboard {
+        1 << self.0
+    }
+
+    // Step `col` columns and `row` rows away, or None when that walks off the board
+    pub const fn offset(self, col: i8, row: i8) -> Option<Square> {
+        let target_col = self.col() as i8 + col;
+        let target_row = self.row() as i8 + row;
+        if target_col < 0 || target_col >= 8 || target_row < 0 || target_row >= 8 {
+            return None;
+        }
+        Some(Square::new(target_col as usize, target_row as usize))
+    }
+
+    // The square in the same place when the board is turned around, e.g. a8 for h1
+    pub const fn rotated(self) -> Square {
+        Square(63 - self.0)
+    }
+
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square::from_index)
+    }
+
+    // Algebraic name like "e2"
+    pub fn parse(name: &str) -> Option<Square> {
+        let bytes = name.as_bytes();
+        if bytes.len() != 2 || !(b'a'..=b'h').contains(&bytes[0]) || !(b'1'..=b'8').contains(&bytes[1]) {
+            return None;
+        }
+
+        Some(Square::new((bytes[0] - b'a') as usize, (b'8' - bytes[1]) as usize))
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.col() as u8) as char, 8 - self.row())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_square_names() {
+        assert_eq!(Square::new(4, 6).to_string(), "e2");
+        assert_eq!(Square::parse("a8"), Some(Square::new(0, 0)));
+        assert_eq!(Square::parse("h1"), Some(Square::new(7, 7)));
+        assert_eq!(Square::parse("h1").map(Square::index), Some(63));
+        assert_eq!(Square::parse("i1"), None);
+    }
+
+    #[test]
+    fn test_offset() {
+        let e2 = Square::new(4, 6);
+        assert_eq!(e2.offset(0, -2), Square::parse("e4"));
+        assert_eq!(e2.offset(4, 0), None);
+        assert_eq!(e2.offset(0, 2), None);
+    }
+
+    #[test]
+    fn test_rotated() {
+        assert_eq!(Square::parse("e2").map(Square::rotated), Square::parse("d7"));
+        assert_eq!(Square::parse("a8").map(Square::rotated), Square::parse("h1"));
+    }
+}
@@ -0,0 +1,154 @@
+use crate::board::bitboard::{squares, PAWN_ATTACKS};
+use crate::board::piece::{Owner, Piece, PieceType};
+use crate::board::position::{CastlingRights, Position};
+use crate::board::square::Square;
+
+// Random keys for every feature of a position. A position's hash is the XOR
+// of the keys of its features, so moves update it by XORing keys in and out.
+struct Keys {
+    pieces: [[[u64; 64]; 6]; 2],
+    castling: [u64; 4],
+    en_passant: [u64; 8],
+    black_to_move: u64,
+}
+
+// SplitMix64, run at compile time so the keys are the same on every run
+const fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys() -> Keys {
+    let mut state = 0x5275_7374_7943_6873;
+    let mut keys = Keys { pieces: [[[0; 64]; 6]; 2], castling: [0; 4], en_passant: [0; 8], black_to_move: 0 };
+
+    let mut owner = 0;
+    while owner < 2 {
+        let mut piece_type = 0;
+        while piece_type < 6 {
+            let mut square = 0;
+            while square < 64 {
+                keys.pieces[owner][piece_type][square] = next_random(&mut state);
+                square += 1;
+            }
+            piece_type += 1;
+        }
+        owner += 1;
+    }
+
+    let mut i = 0;
+    while i < 4 {
+        keys.castling[i] = next_random(&mut state);
+        i += 1;
+    }
+    i = 0;
+    while i < 8 {
+        keys.en_passant[i] = next_random(&mut state);
+        i += 1;
+    }
+    keys.black_to_move = next_random(&mut state);
+    keys
+}
+
+const KEYS: Keys = generate_keys();
+
+pub fn piece_key(piece: Piece, square: Square) -> u64 {
+    KEYS.pieces[piece.owner as usize][piece.piece_type as usize][square.index()]
+}
+
+pub fn castling_key(castling: CastlingRights) -> u64 {
+    let rights = [castling.white_king_side, castling.white_queen_side, castling.black_king_side, castling.black_queen_side];
+    rights
+        .iter()
+        .zip(KEYS.castling)
+        .filter(|(&allowed, _)| allowed)
+        .fold(0, |hash, (_, key)| hash ^ key)
+}
+
+pub fn turn_key(turn: Owner) -> u64 {
+    match turn {
+        Owner::White => 0,
+        Owner::Black => KEYS.black_to_move,
+    }
+}
+
+impl Position {
+
+    // The en passant file only counts when a pawn stands ready to take on it,
+    // otherwise the square makes no difference to what can happen next
+    pub fn en_passant_file(&self) -> Option<usize> {
+        match self.en_passant {
+            Some(target) if PAWN_ATTACKS[self.turn.opponent() as usize][target.index()] & self.bitboard(self.turn, PieceType::Pawn) != 0 => {
+                Some(target.col())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn en_passant_key(&self) -> u64 {
+        self.en_passant_file().map_or(0, |file| KEYS.en_passant[file])
+    }
+
+    // The hash worked out from scratch. `hash` should always be equal to it.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = castling_key(self.castling) ^ self.en_passant_key() ^ turn_key(self.turn);
+        for owner in [Owner::Black, Owner::White] {
+            for piece_type in PieceType::ALL {
+                for square in squares(self.bitboard(owner, piece_type)) {
+                    hash ^= piece_key(Piece::new(owner, piece_type), square);
+                }
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Play every line two moves deep and make sure the incremental hash keeps up
+    #[test]
+    fn test_incremental_hash() {
+        let kiwipete = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for position in [Position::default(), kiwipete] {
+            for mv in position.legal_moves() {
+                let mut child = position;
+                child.apply_move(mv);
+                assert_eq!(child.hash, child.zobrist_hash());
+
+                for reply in child.legal_moves() {
+                    let mut grandchild = child;
+                    grandchild.apply_move(reply);
+                    assert_eq!(grandchild.hash, grandchild.zobrist_hash());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_transpositions_hash_the_same() {
+        let mut knights_first = Position::default();
+        let mut pawns_first = Position::default();
+        for san in ["Nf3", "Nf6", "e3", "e6"] {
+            knights_first.apply_move(knights_first.parse_san(san).unwrap());
+        }
+        for san in ["e3", "e6", "Nf3", "Nf6"] {
+            pawns_first.apply_move(pawns_first.parse_san(san).unwrap());
+        }
+
+        assert_eq!(knights_first.hash, pawns_first.hash);
+        assert_ne!(knights_first.hash, Position::default().hash);
+    }
+
+    #[test]
+    fn test_en_passant_only_counts_when_possible() {
+        let mut position = Position::default();
+        position.apply_move(position.parse_san("e4").unwrap());
+        // No black pawn can take on e3
+        assert_eq!(position.hash, Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap().hash);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::board::piece::Owner;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimeControl {
+    // The whole game has to be played in `base`
+    SuddenDeath { base: Duration },
+    // `increment` is added to the clock after every move
+    Fischer { base: Duration, increment: Duration },
+    // Up to `delay` of each move's time is given back afterwards, so moving
+    // inside the delay costs nothing but time is never gained
+    Bronstein { base: Duration, delay: Duration },
+}
+
+impl TimeControl {
+    pub fn base(self) -> Duration {
+        match self {
+            TimeControl::SuddenDeath { base } => base,
+            TimeControl::Fischer { base, .. } => base,
+            TimeControl::Bronstein { base, .. } => base,
+        }
+    }
+
+    // Time that comes back after each move, at most
+    pub fn bonus(self) -> Duration {
+        match self {
+            TimeControl::SuddenDeath { .. } => Duration::ZERO,
+            TimeControl::Fischer { increment, .. } => increment,
+            TimeControl::Bronstein { delay, .. } => delay,
+        }
+    }
+}
+
+// Minutes, then "+increment" or "d delay" in seconds, e.g. "3+2" or "5 d3"
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let minutes = self.base().as_secs() / 60;
+        match self {
+            TimeControl::SuddenDeath { .. } => write!(f, "{}+0", minutes),
+            TimeControl::Fischer { increment, .. } => write!(f, "{}+{}", minutes, increment.as_secs()),
+            TimeControl::Bronstein { delay, .. } => write!(f, "{} d{}", minutes, delay.as_secs()),
+        }
+    }
+}
+
+// Moves left to plan for when nobody says how many there are
+pub const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// A share of the time left to spend on the next move, for engines. Spends an
+// even share of the clock, plus most of what comes back after the move.
+pub fn think_time(remaining: Duration, bonus: Duration, moves_to_go: u32) -> Duration {
+    // Never plan to use the last of the clock, there is always some lag
+    let most = remaining.saturating_sub(Duration::from_millis(50)).max(Duration::from_millis(10));
+    (remaining / moves_to_go.max(1) + bonus * 3 / 4).min(most)
+}
+
+// Both players' clocks. Times are passed in rather than read, so the clock
+// can be driven by the frame loop and by tests alike.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Clock {
+    control: TimeControl,
+    // Time left for each side when its current turn started, indexed by `Owner`
+    remaining: [Duration; 2],
+    // Whose clock is running, and since when
+    running: Option<(Owner, Instant)>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock { control, remaining: [control.base(); 2], running: None }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    pub fn running(&self) -> Option<Owner> {
+        self.running.map(|(owner, _)| owner)
+    }
+
+    pub fn start(&mut self, turn: Owner, now: Instant) {
+        if self.running.is_none() {
+            self.running = Some((turn, now));
+        }
+    }
+
+    // Stop the clock, keeping the time used on the current turn
+    pub fn pause(&mut self, now: Instant) {
+        if let Some((owner, _)) = self.running {
+            self.remaining[owner as usize] = self.remaining(owner, now);
+            self.running = None;
+        }
+    }
+
+    pub fn remaining(&self, owner: Owner, now: Instant) -> Duration {
+        let left = self.remaining[owner as usize];
+        match self.running {
+            Some((running, since)) if running == owner => left.saturating_sub(now.saturating_duration_since(since)),
+            _ => left,
+        }
+    }
+
+    // Take the other side's word for its time, e.g. from a clock on another machine.
+    // Only applies between turns, while that side's clock isn't running.
+    pub fn set_remaining(&mut self, owner: Owner, remaining: Duration) {
+        if self.running() != Some(owner) {
+            self.remaining[owner as usize] = remaining;
+        }
+    }
+
+    // The side whose clock is running has moved: charge its time, hand out
+    // the increment or delay, and start the other side's clock
+    pub fn press(&mut self, now: Instant) {
+        let (owner, since) = match self.running {
+            Some(running) => running,
+            None => return,
+        };
+        let used = now.saturating_duration_since(since);
+        let left = self.remaining(owner, now);
+
+        self.remaining[owner as usize] = match self.control {
+            TimeControl::SuddenDeath { .. } => left,
+            TimeControl::Fischer { increment, .. } => left + increment,
+            TimeControl::Bronstein { delay, .. } => left + used.min(delay),
+        };
+        self.running = Some((owner.opponent(), now));
+    }
+
+    // The side that has run out of time, if any
+    pub fn flagged(&self, now: Instant) -> Option<Owner> {
+        [Owner::White, Owner::Black].into_iter().find(|&owner| self.remaining(owner, now).is_zero())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn seconds(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn test_fischer_increment() {
+        let start = Instant::now();
+        let mut clock = Clock::new(TimeControl::Fischer { base: seconds(60), increment: seconds(2) });
+        clock.start(Owner::White, start);
+
+        clock.press(start + seconds(10));
+        assert_eq!(clock.remaining(Owner::White, start + seconds(10)), seconds(52));
+        assert_eq!(clock.running(), Some(Owner::Black));
+        assert_eq!(clock.remaining(Owner::Black, start + seconds(15)), seconds(55));
+    }
+
+    #[test]
+    fn test_bronstein_delay() {
+        let start = Instant::now();
+        let mut clock = Clock::new(TimeControl::Bronstein { base: seconds(60), delay: seconds(3) });
+        clock.start(Owner::White, start);
+
+        // A quick move costs nothing, a slow one costs all but the delay
+        clock.press(start + seconds(2));
+        assert_eq!(clock.remaining(Owner::White, start + seconds(2)), seconds(60));
+        clock.press(start + seconds(12));
+        assert_eq!(clock.remaining(Owner::Black, start + seconds(12)), seconds(53));
+    }
+
+    #[test]
+    fn test_flag_falls() {
+        let start = Instant::now();
+        let mut clock = Clock::new(TimeControl::SuddenDeath { base: seconds(60) });
+        clock.start(Owner::White, start);
+
+        assert_eq!(clock.flagged(start + seconds(59)), None);
+        assert_eq!(clock.flagged(start + seconds(61)), Some(Owner::White));
+
+        clock.pause(start + seconds(30));
+        assert_eq!(clock.flagged(start + seconds(1000)), None);
+        assert_eq!(clock.remaining(Owner::White, start + seconds(1000)), seconds(30));
+    }
+
+    #[test]
+    fn test_think_time() {
+        assert_eq!(think_time(seconds(60), seconds(1), 30), Duration::from_millis(2750));
+        assert_eq!(think_time(Duration::from_millis(100), Duration::ZERO, 1), Duration::from_millis(50));
+    }
+}
@@ -1 +1,13 @@
+pub mod button;
+pub mod checkbox;
+pub mod dropdown;
+pub mod editor;
+pub mod focus;
+pub mod label;
 pub mod menu;
+pub mod panel;
+pub mod settings;
+pub mod slider;
+pub mod text_box;
+pub mod viewport;
+pub mod widget;
@@ -1,28 +1,116 @@
 use raylib::prelude::*;
 use raylib::consts::MouseButton::*;
+use crate::components::focus::Focus;
+use crate::components::widget::{activated, draw_centered_text, draw_focus, Widget, FONT_SIZE};
 
-const MENU_HEIGHT: i32 = 450;
-const MENU_WIDTH: i32 = 684;
-
+const LARGE_FONT_SIZE: i32 = 24;
 
+// A button with some text on it. It is clicked when the mouse is let go over
+// it after being pressed on it, or by Enter or Space while it has the keyboard.
 pub struct Button {
-    text: String,
-    width: u8,
-    height: u8,
+    rect: Rectangle,
+    pub text: String,
+    font_size: i32,
+    // Drawn dark, for the chosen one out of a row of choices
+    pub selected: bool,
+    // A disabled button is drawn faded and can't be clicked
+    pub enabled: bool,
+    hovered: bool,
+    pressed: bool,
+    focused: bool,
+}
+
+impl Button {
+    pub fn new(rect: Rectangle, text: &str) -> Button {
+        Button {
+            rect,
+            text: text.to_string(),
+            font_size: FONT_SIZE,
+            selected: false,
+            enabled: true,
+            hovered: false,
+            pressed: false,
+            focused: false,
+        }
+    }
 
+    // The bigger buttons along the bottom of the menus
+    pub fn large(rect: Rectangle, text: &str) -> Button {
+        Button { font_size: LARGE_FONT_SIZE, ..Button::new(rect, text) }
+    }
 }
 
+impl Widget for Button {
+    fn rect(&self) -> Rectangle {
+        self.rect
+    }
 
-impl Button {
+    fn focused(&self) -> bool {
+        self.focused
+    }
 
-    fn new(d: &mut RaylibDrawHandle, text: String, width: u8, height: u8) {
-        
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
 
-        Button {
-            text: text,
-            width: width,
-            height: height,
+    fn update(&mut self, d: &mut RaylibDrawHandle, hovered: bool) -> bool {
+        self.hovered = hovered && self.enabled;
+        if !self.enabled {
+            self.pressed = false;
+            return false;
+        }
+
+        if hovered && d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+            self.pressed = true;
+        }
+        let clicked = self.pressed && hovered && d.is_mouse_button_released(MOUSE_BUTTON_LEFT);
+        if !d.is_mouse_button_down(MOUSE_BUTTON_LEFT) {
+            self.pressed = false;
+        }
+        clicked || activated(d, self.focused)
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle) {
+        let (background, foreground) = if !self.enabled {
+            (Color::LIGHTGRAY, Color::GRAY)
+        } else if self.selected || (self.pressed && self.hovered) {
+            (Color::DARKGRAY, Color::WHITE)
+        } else if self.hovered {
+            (Color::LIGHTGRAY, Color::BLACK)
+        } else {
+            (Color::GRAY, Color::BLACK)
+        };
+        d.draw_rectangle_rec(self.rect, background);
+        draw_centered_text(d, self.rect, &self.text, self.font_size, foreground);
+        if self.focused {
+            draw_focus(d, self.rect);
         }
     }
+}
+
+// A row of buttons for picking one of `names`, the first one in `rect` and
+// the others along from it `gap` apart
+pub fn choice_row(rect: Rectangle, gap: f32, names: &[&str]) -> Vec<Button> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| Button::new(Rectangle { x: rect.x + i as f32 * (rect.width + gap), ..rect }, name))
+        .collect()
+}
 
+// Update and draw a row of choices with the `selected` one marked,
+// returning which was clicked this frame
+pub fn update_choices(d: &mut RaylibDrawHandle, focus: &mut Focus, buttons: &mut [Button], selected: usize) -> Option<usize> {
+    let mut clicked = None;
+    for (i, button) in buttons.iter_mut().enumerate() {
+        if focus.update(d, button) {
+            clicked = Some(i);
+        }
+    }
+    let selected = clicked.unwrap_or(selected);
+    for (i, button) in buttons.iter_mut().enumerate() {
+        button.selected = i == selected;
+        button.draw(d);
+    }
+    clicked
 }
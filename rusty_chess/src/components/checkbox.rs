@@ -0,0 +1,79 @@
+use raylib::prelude::*;
+use raylib::consts::MouseButton::*;
+use crate::components::widget::{activated, draw_focus, Widget, FONT_SIZE};
+
+// A box to tick with its text beside it. Clicking either toggles it, like
+// Enter or Space while it has the keyboard.
+pub struct Checkbox {
+    rect: Rectangle,
+    text: String,
+    pub checked: bool,
+    hovered: bool,
+    pressed: bool,
+    focused: bool,
+}
+
+impl Checkbox {
+    pub fn new(rect: Rectangle, text: &str, checked: bool) -> Checkbox {
+        Checkbox { rect, text: text.to_string(), checked, hovered: false, pressed: false, focused: false }
+    }
+
+    // Square, as tall as the whole widget, at its left end
+    fn box_rect(&self) -> Rectangle {
+        Rectangle { width: self.rect.height, ..self.rect }
+    }
+}
+
+impl Widget for Checkbox {
+    fn rect(&self) -> Rectangle {
+        self.rect
+    }
+
+    fn focused(&self) -> bool {
+        self.focused
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn update(&mut self, d: &mut RaylibDrawHandle, hovered: bool) -> bool {
+        self.hovered = hovered;
+        if hovered && d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+            self.pressed = true;
+        }
+        // Only a click that started on it counts, not something dragged over it
+        let clicked = self.pressed && hovered && d.is_mouse_button_released(MOUSE_BUTTON_LEFT);
+        if !d.is_mouse_button_down(MOUSE_BUTTON_LEFT) {
+            self.pressed = false;
+        }
+        let toggled = clicked || activated(d, self.focused);
+        if toggled {
+            self.checked = !self.checked;
+        }
+        toggled
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle) {
+        let box_rect = self.box_rect();
+        d.draw_rectangle_rec(box_rect, if self.hovered { Color::LIGHTGRAY } else { Color::RAYWHITE });
+        d.draw_rectangle_lines_ex(box_rect, 2.0, Color::DARKGRAY);
+        if self.checked {
+            let inset = box_rect.width / 4.0;
+            let mark = Rectangle {
+                x: box_rect.x + inset,
+                y: box_rect.y + inset,
+                width: box_rect.width - 2.0 * inset,
+                height: box_rect.height - 2.0 * inset,
+            };
+            d.draw_rectangle_rec(mark, Color::DARKGRAY);
+        }
+
+        let x = (box_rect.x + box_rect.width) as i32 + 8;
+        let y = (self.rect.y + self.rect.height / 2.0) as i32 - FONT_SIZE / 2;
+        d.draw_text(&self.text, x, y, FONT_SIZE, Color::BLACK);
+        if self.focused {
+            draw_focus(d, self.rect);
+        }
+    }
+}
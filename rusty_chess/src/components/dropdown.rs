@@ -0,0 +1,135 @@
+use raylib::prelude::*;
+use raylib::consts::KeyboardKey::*;
+use raylib::consts::MouseButton::*;
+use crate::components::widget::{activated, draw_focus, Widget, FONT_SIZE};
+
+// One choice out of a list that opens underneath it when clicked. With the
+// keyboard, Up and Down change the choice and Enter or Space opens the list.
+pub struct Dropdown {
+    rect: Rectangle,
+    options: Vec<String>,
+    pub selected: usize,
+    open: bool,
+    // The option under the mouse in the open list
+    hovered_option: Option<usize>,
+    pressed: bool,
+    focused: bool,
+}
+
+impl Dropdown {
+    // There has to be something to choose from
+    pub fn new(rect: Rectangle, options: Vec<String>, selected: usize) -> Dropdown {
+        assert!(!options.is_empty(), "a dropdown needs at least one option");
+        let selected = selected.min(options.len() - 1);
+        Dropdown { rect, options, selected, open: false, hovered_option: None, pressed: false, focused: false }
+    }
+
+    fn option_rect(&self, i: usize) -> Rectangle {
+        Rectangle { y: self.rect.y + (i + 1) as f32 * self.rect.height, ..self.rect }
+    }
+
+    fn option_at(&self, point: Vector2) -> Option<usize> {
+        (0..self.options.len()).find(|&i| self.option_rect(i).check_collision_point_rec(point))
+    }
+}
+
+impl Widget for Dropdown {
+    fn rect(&self) -> Rectangle {
+        self.rect
+    }
+
+    fn overlay(&self) -> Option<Rectangle> {
+        if !self.open {
+            return None;
+        }
+        let height = self.options.len() as f32 * self.rect.height;
+        Some(Rectangle { y: self.rect.y + self.rect.height, height, ..self.rect })
+    }
+
+    fn focused(&self) -> bool {
+        self.focused
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused {
+            self.open = false;
+        }
+    }
+
+    fn update(&mut self, d: &mut RaylibDrawHandle, hovered: bool) -> bool {
+        let old = self.selected;
+        let mouse = d.get_mouse_position();
+        self.hovered_option = if self.open && hovered { self.option_at(mouse) } else { None };
+
+        if hovered && d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+            self.pressed = true;
+        }
+        let clicked = self.pressed && hovered && d.is_mouse_button_released(MOUSE_BUTTON_LEFT);
+        if !d.is_mouse_button_down(MOUSE_BUTTON_LEFT) {
+            self.pressed = false;
+        }
+        if clicked {
+            match self.hovered_option {
+                Some(i) => {
+                    self.selected = i;
+                    self.open = false;
+                }
+                None => self.open = !self.open,
+            }
+        }
+
+        if activated(d, self.focused) {
+            self.open = !self.open;
+        } else if self.focused && d.is_key_pressed(KEY_ESCAPE) {
+            self.open = false;
+        } else if self.focused && d.is_key_pressed(KEY_DOWN) {
+            self.selected = (self.selected + 1).min(self.options.len().saturating_sub(1));
+        } else if self.focused && d.is_key_pressed(KEY_UP) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected != old
+    }
+
+    // Screens draw their dropdowns last so an open list lies over everything else
+    fn draw(&self, d: &mut RaylibDrawHandle) {
+        d.draw_rectangle_rec(self.rect, Color::GRAY);
+        d.draw_rectangle_lines_ex(self.rect, 1.0, Color::DARKGRAY);
+        let x = self.rect.x as i32 + 10;
+        let text_y = |rect: Rectangle| (rect.y + rect.height / 2.0) as i32 - FONT_SIZE / 2;
+        // `selected` is public, so it may have been set past the end since
+        let text = self.options.get(self.selected).or(self.options.last()).map_or("", String::as_str);
+        d.draw_text(text, x, text_y(self.rect), FONT_SIZE, Color::BLACK);
+
+        // A small arrow at the right end, pointing the way the list opens
+        let right = self.rect.x + self.rect.width;
+        let middle = self.rect.y + self.rect.height / 2.0;
+        d.draw_triangle(
+            Vector2 { x: right - 26.0, y: middle - 4.0 },
+            Vector2 { x: right - 16.0, y: middle + 6.0 },
+            Vector2 { x: right - 6.0, y: middle - 4.0 },
+            Color::BLACK,
+        );
+
+        if self.open {
+            for (i, option) in self.options.iter().enumerate() {
+                let rect = self.option_rect(i);
+                let (background, foreground) = if i == self.selected {
+                    (Color::DARKGRAY, Color::WHITE)
+                } else if self.hovered_option == Some(i) {
+                    (Color::LIGHTGRAY, Color::BLACK)
+                } else {
+                    (Color::RAYWHITE, Color::BLACK)
+                };
+                d.draw_rectangle_rec(rect, background);
+                d.draw_text(option, x, text_y(rect), FONT_SIZE, foreground);
+            }
+            if let Some(list) = self.overlay() {
+                d.draw_rectangle_lines_ex(list, 1.0, Color::DARKGRAY);
+            }
+        }
+        if self.focused {
+            draw_focus(d, self.rect);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use raylib::prelude::*;
+use raylib::consts::MouseButton::*;
+use crate::components::button::{choice_row, update_choices, Button};
+use crate::components::checkbox::Checkbox;
+use crate::components::focus::Focus;
+use crate::components::label::Label;
+use crate::components::menu::option_rect;
+use crate::components::slider::Slider;
+use crate::components::widget::Widget;
+use crate::game::{GameState, SPACE_SIZE};
+use crate::theme::PieceSet;
+use rusty_chess::board::piece::{Owner, Piece, PieceType};
+use rusty_chess::board::position::Position;
+use rusty_chess::board::square::Square;
+
+// Every piece to pick up, white's on the top row and black's underneath
+const PALETTE_X: f32 = 500.0;
+const PALETTE_Y: f32 = 10.0;
+const PALETTE_OWNERS: [Owner; 2] = [Owner::White, Owner::Black];
+
+// The other options go down the right of the board, labels then buttons
+const LABEL_X: i32 = 500;
+const OPTION_X: f32 = 620.0;
+const OPTION_WIDTH: f32 = 155.0;
+
+const TURNS: [Owner; 2] = [Owner::White, Owner::Black];
+// Move numbers the slider goes up to, from the first
+const MAX_MOVE_NUMBER: i32 = 200;
+
+// A position being set up by hand, kept between visits to the editor
+pub struct Editor {
+    pub position: Position,
+    // The piece following the mouse, taken from the palette or lifted off the board
+    held: Option<Piece>,
+    // Whether the FEN was copied, or why it couldn't be
+    message: Option<String>,
+
+    focus: Focus,
+    labels: Vec<Label>,
+    turn_buttons: Vec<Button>,
+    // White's king side and queen side, then black's
+    castling: Vec<Checkbox>,
+    move_number: Slider,
+    clear: Button,
+    starting_position: Button,
+    play: Button,
+    analyse: Button,
+    copy_fen: Button,
+    back: Button,
+}
+
+// What to do with the position once it is set up
+pub enum EditorAction {
+    Play(Position),
+    Analyse(Position),
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        let castling_rect = |col: usize, row: usize| Rectangle {
+            x: OPTION_X + col as f32 * (OPTION_WIDTH + 10.0),
+            y: 195.0 + row as f32 * 40.0,
+            width: OPTION_WIDTH,
+            height: 30.0,
+        };
+        Editor {
+            position: Position::default(),
+            held: None,
+            message: None,
+            focus: Focus::new(),
+            labels: vec![
+                Label::new("To move", LABEL_X, 150, 24, Color::BLACK),
+                Label::new("Castling", LABEL_X, 198, 24, Color::BLACK),
+                Label::new("Move", LABEL_X, 278, 24, Color::BLACK),
+            ],
+            turn_buttons: choice_row(option_rect(OPTION_X, 140.0, OPTION_WIDTH), 10.0, &["White", "Black"]),
+            castling: vec![
+                Checkbox::new(castling_rect(0, 0), "White O-O", true),
+                Checkbox::new(castling_rect(1, 0), "White O-O-O", true),
+                Checkbox::new(castling_rect(0, 1), "Black O-O", true),
+                Checkbox::new(castling_rect(1, 1), "Black O-O-O", true),
+            ],
+            move_number: Slider::new(Rectangle { x: OPTION_X, y: 275.0, width: 320.0, height: 30.0 }, 1, MAX_MOVE_NUMBER, 1),
+            clear: Button::new(option_rect(500.0, 320.0, 215.0), "Clear board"),
+            starting_position: Button::new(option_rect(725.0, 320.0, 215.0), "Starting position"),
+            play: Button::new(option_rect(500.0, 395.0, 140.0), "Play"),
+            analyse: Button::new(option_rect(650.0, 395.0, 140.0), "Analyse"),
+            copy_fen: Button::new(option_rect(800.0, 395.0, 140.0), "Copy FEN"),
+            back: Button::new(option_rect(500.0, 445.0, 440.0), "Back"),
+        }
+    }
+
+    // Anything moved makes an en passant capture impossible
+    fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        self.position.set_piece(square, piece);
+        self.position.en_passant = None;
+        self.position.refresh_hash();
+        self.message = None;
+    }
+
+    fn reset(&mut self, position: Position) {
+        self.position = position;
+        self.position.refresh_hash();
+        self.held = None;
+        self.message = None;
+    }
+}
+
+fn palette_rect(owner_row: usize, i: usize) -> Rectangle {
+    Rectangle {
+        x: PALETTE_X + i as f32 * SPACE_SIZE,
+        y: PALETTE_Y + owner_row as f32 * SPACE_SIZE,
+        width: SPACE_SIZE,
+        height: SPACE_SIZE,
+    }
+}
+
+fn space_rect(square: Square) -> Rectangle {
+    Rectangle {
+        x: square.col() as f32 * SPACE_SIZE,
+        y: square.row() as f32 * SPACE_SIZE,
+        width: SPACE_SIZE,
+        height: SPACE_SIZE,
+    }
+}
+
+fn square_at(point: Vector2) -> Option<Square> {
+    let col = (point.x / SPACE_SIZE).floor();
+    let row = (point.y / SPACE_SIZE).floor();
+    if !(0.0..8.0).contains(&col) || !(0.0..8.0).contains(&row) {
+        return None;
+    }
+    Some(Square::new(col as usize, row as usize))
+}
+
+fn palette_piece_at(point: Vector2) -> Option<Piece> {
+    for (owner_row, owner) in PALETTE_OWNERS.into_iter().enumerate() {
+        for (i, piece_type) in PieceType::ALL.into_iter().enumerate() {
+            if palette_rect(owner_row, i).check_collision_point_rec(point) {
+                return Some(Piece::new(owner, piece_type));
+            }
+        }
+    }
+    None
+}
+
+// Pieces are dragged from the palette onto the board, around the board and
+// off it again. A right click takes a piece off too.
+fn update_pieces(d: &RaylibDrawHandle, editor: &mut Editor) {
+    let mouse = d.get_mouse_position();
+
+    if d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+        if let Some(piece) = palette_piece_at(mouse) {
+            editor.held = Some(piece);
+        } else if let Some(square) = square_at(mouse) {
+            if let Some(piece) = editor.position.piece_at(square) {
+                editor.held = Some(piece);
+                editor.set_piece(square, None);
+            }
+        }
+    }
+
+    if d.is_mouse_button_released(MOUSE_BUTTON_LEFT) {
+        // Let go anywhere else and the piece is gone
+        if let (Some(piece), Some(square)) = (editor.held, square_at(mouse)) {
+            editor.set_piece(square, Some(piece));
+        }
+        editor.held = None;
+    }
+
+    if d.is_mouse_button_pressed(MOUSE_BUTTON_RIGHT) {
+        if let Some(square) = square_at(mouse).filter(|&square| editor.position.piece_at(square).is_some()) {
+            editor.set_piece(square, None);
+        }
+    }
+}
+
+fn draw_board(d: &mut RaylibDrawHandle, editor: &Editor, pieces: &PieceSet) {
+    for square in Square::all() {
+        let color = if (square.col() + square.row()) % 2 == 0 { Color::LIGHTGRAY } else { Color::GRAY };
+        d.draw_rectangle_rec(space_rect(square), color);
+        if let Some(piece) = editor.position.piece_at(square) {
+            pieces.draw(d, piece, space_rect(square));
+        }
+    }
+
+    for (owner_row, owner) in PALETTE_OWNERS.into_iter().enumerate() {
+        for (i, piece_type) in PieceType::ALL.into_iter().enumerate() {
+            let rect = palette_rect(owner_row, i);
+            d.draw_rectangle_rec(rect, if (owner_row + i) % 2 == 0 { Color::LIGHTGRAY } else { Color::GRAY });
+            pieces.draw(d, Piece::new(owner, piece_type), rect);
+        }
+    }
+}
+
+fn update_options(d: &mut RaylibDrawHandle, editor: &mut Editor) {
+    let mut changed = false;
+    for label in &editor.labels {
+        label.draw(d);
+    }
+
+    let selected = TURNS.iter().position(|&owner| owner == editor.position.turn).unwrap_or(0);
+    if let Some(i) = update_choices(d, &mut editor.focus, &mut editor.turn_buttons, selected) {
+        editor.position.turn = TURNS[i];
+        changed = true;
+    }
+
+    // The boxes show the position's rights, which clearing the board takes away
+    let castling = &mut editor.position.castling;
+    let rights = [&mut castling.white_king_side, &mut castling.white_queen_side, &mut castling.black_king_side, &mut castling.black_queen_side];
+    for (checkbox, allowed) in editor.castling.iter_mut().zip(rights) {
+        checkbox.checked = *allowed;
+        if editor.focus.update(d, checkbox) {
+            *allowed = checkbox.checked;
+            changed = true;
+        }
+        checkbox.draw(d);
+    }
+
+    editor.move_number.value = (editor.position.fullmove_number as i32).min(MAX_MOVE_NUMBER);
+    if editor.focus.update(d, &mut editor.move_number) {
+        editor.position.fullmove_number = editor.move_number.value as u32;
+        changed = true;
+    }
+    editor.move_number.draw(d);
+
+    if changed {
+        editor.position.refresh_hash();
+        editor.message = None;
+    }
+
+    if editor.focus.update(d, &mut editor.clear) {
+        let mut empty = Position::empty();
+        empty.turn = editor.position.turn;
+        editor.reset(empty);
+    }
+    editor.clear.draw(d);
+    if editor.focus.update(d, &mut editor.starting_position) {
+        editor.reset(Position::default());
+    }
+    editor.starting_position.draw(d);
+}
+
+// Returns what was picked to do with the position this frame, if anything
+pub fn create_editor(d: &mut RaylibDrawHandle, game_state: &mut GameState, editor: &mut Editor, pieces: &PieceSet) -> Option<EditorAction> {
+    editor.focus.begin(d);
+    update_pieces(d, editor);
+    draw_board(d, editor, pieces);
+    update_options(d, editor);
+
+    let validity = editor.position.validate();
+    match &validity {
+        Ok(()) => Label::new("Ready to play", LABEL_X, 370, 20, Color::DARKGREEN).draw(d),
+        Err(e) => Label::new(&format!("Not legal: {}", e), LABEL_X, 370, 20, Color::RED).draw(d),
+    }
+
+    // A position that couldn't come up in a game can still be copied out
+    let mut action = None;
+    editor.play.enabled = validity.is_ok();
+    editor.analyse.enabled = validity.is_ok();
+    if editor.focus.update(d, &mut editor.play) {
+        action = Some(EditorAction::Play(editor.position));
+    }
+    if editor.focus.update(d, &mut editor.analyse) {
+        action = Some(EditorAction::Analyse(editor.position));
+    }
+    if editor.focus.update(d, &mut editor.copy_fen) {
+        editor.message = Some(match d.set_clipboard_text(&editor.position.to_fen()) {
+            Ok(()) => "FEN copied".to_string(),
+            Err(e) => format!("Could not copy FEN: {}", e),
+        });
+    }
+    if editor.focus.update(d, &mut editor.back) {
+        editor.held = None;
+        editor.message = None;
+        *game_state = GameState::Menu;
+    }
+    for button in [&editor.play, &editor.analyse, &editor.copy_fen, &editor.back] {
+        button.draw(d);
+    }
+
+    Label::new(&editor.position.to_fen(), 10, 500, 16, Color::DARKGRAY).draw(d);
+    if let Some(message) = &editor.message {
+        Label::new(message, 10, 520, 16, Color::DARKBLUE).draw(d);
+    }
+
+    // The held piece is drawn last, over everything else
+    if let Some(piece) = editor.held {
+        let mouse = d.get_mouse_position();
+        let dest = Rectangle { x: mouse.x - SPACE_SIZE / 2.0, y: mouse.y - SPACE_SIZE / 2.0, width: SPACE_SIZE, height: SPACE_SIZE };
+        pieces.draw(d, piece, dest);
+    }
+
+    action
+}
@@ -0,0 +1,72 @@
+use raylib::prelude::*;
+use raylib::consts::KeyboardKey::*;
+use raylib::consts::MouseButton::*;
+use crate::components::widget::Widget;
+
+// Which of a screen's widgets has the keyboard. Widgets are numbered in the
+// order they are updated each frame, which is also the order Tab goes through
+// them in, with a stop at none of them between the last and the first.
+pub struct Focus {
+    current: Option<usize>,
+    // Widgets updated so far this frame, and in all of last frame
+    next: usize,
+    count: usize,
+    // An open dropdown's list keeps the mouse from whatever is under it.
+    // Last frame's is used, since this frame's may not have been updated yet.
+    overlay: Option<(usize, Rectangle)>,
+    next_overlay: Option<(usize, Rectangle)>,
+}
+
+impl Focus {
+    pub fn new() -> Focus {
+        Focus { current: None, next: 0, count: 0, overlay: None, next_overlay: None }
+    }
+
+    // Call once a frame, before updating any of the widgets
+    pub fn begin(&mut self, d: &RaylibDrawHandle) {
+        self.count = self.next;
+        self.next = 0;
+        self.overlay = self.next_overlay.take();
+
+        if d.is_key_pressed(KEY_TAB) && self.count > 0 {
+            let back = d.is_key_down(KEY_LEFT_SHIFT) || d.is_key_down(KEY_RIGHT_SHIFT);
+            self.current = match (self.current, back) {
+                (None, false) => Some(0),
+                (None, true) => Some(self.count - 1),
+                (Some(i), false) if i + 1 < self.count => Some(i + 1),
+                (Some(i), true) if i > 0 => Some(i - 1),
+                _ => None,
+            };
+        }
+
+        // A click takes the keyboard away, unless it lands on a widget that takes it back
+        if d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+            self.current = None;
+        }
+    }
+
+    // Update the next widget, returning whether it was used
+    pub fn update<W: Widget>(&mut self, d: &mut RaylibDrawHandle, widget: &mut W) -> bool {
+        let index = self.next;
+        self.next += 1;
+
+        let mouse = d.get_mouse_position();
+        let covered = self.overlay.is_some_and(|(owner, rect)| owner != index && rect.check_collision_point_rec(mouse));
+        let hovered = !covered
+            && (widget.rect().check_collision_point_rec(mouse) || widget.overlay().is_some_and(|rect| rect.check_collision_point_rec(mouse)));
+        if hovered && d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+            self.current = Some(index);
+        }
+
+        widget.set_focused(self.current == Some(index));
+        let used = widget.update(d, hovered);
+        // Widgets can let go of the keyboard themselves, e.g. on Escape
+        if self.current == Some(index) && !widget.focused() {
+            self.current = None;
+        }
+        if let Some(rect) = widget.overlay() {
+            self.next_overlay = Some((index, rect));
+        }
+        used
+    }
+}
@@ -0,0 +1,20 @@
+use raylib::prelude::*;
+
+// A line of text that only shows something, like the name of a row of options
+pub struct Label {
+    pub text: String,
+    x: i32,
+    y: i32,
+    font_size: i32,
+    pub color: Color,
+}
+
+impl Label {
+    pub fn new(text: &str, x: i32, y: i32, font_size: i32, color: Color) -> Label {
+        Label { text: text.to_string(), x, y, font_size, color }
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        d.draw_text(&self.text, self.x, self.y, self.font_size, self.color);
+    }
+}
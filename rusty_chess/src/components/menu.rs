@@ -1,45 +1,249 @@
 use raylib::prelude::*;
-use raylib::consts::MouseButton::*;
-use raylib::ffi::CheckCollisionPointRec;
+use crate::components::button::{choice_row, update_choices, Button};
+use crate::components::dropdown::Dropdown;
+use crate::components::focus::Focus;
+use crate::components::label::Label;
+use crate::components::panel::Panel;
+use crate::components::text_box::TextBox;
+use crate::components::widget::Widget;
+use crate::game::{Difficulty, GameState, Opponent};
+use rusty_chess::net::DEFAULT_PORT;
+use rusty_chess::board::piece::Owner;
+use rusty_chess::clock::TimeControl;
+use std::time::Duration;
 
-const MENU_HEIGHT: i32 = 450;
-const MENU_WIDTH: i32 = 684;
+pub const PANEL_RECT: Rectangle = Rectangle { x: 138.0, y: 45.0, width: 684.0, height: 450.0 };
 
-const BUTTON_WIDTH: f32 = 200.0;
+const BUTTON_WIDTH: f32 = 150.0;
 const BUTTON_HEIGHT: f32 = 60.0;
 
+const OPTION_HEIGHT: f32 = 40.0;
 
-pub fn create_menu(d: &mut RaylibDrawHandle, game_state: &mut u8) {
-    let _menu_rect = Rectangle {
-        x: 0.0,
-        y: 0.0,
-        width: 512.0,
-        height: 256.0
-    };
-
-    let new_game_rect = Rectangle {
-        x: 240.0, 
-        y: 400.0, 
-        width: BUTTON_WIDTH, 
-        height: BUTTON_HEIGHT
-    };
-
-    if d.is_mouse_button_released(MOUSE_BUTTON_LEFT) {
-        unsafe {
-        if CheckCollisionPointRec(d.get_mouse_position().into(), new_game_rect.into()) {
-            *game_state = 1;
+const ADDRESS_RECT: Rectangle = Rectangle { x: 330.0, y: 350.0, width: 410.0, height: OPTION_HEIGHT };
+
+// Time controls on offer, None being an untimed game
+const TIME_CONTROLS: [Option<TimeControl>; 5] = [
+    None,
+    Some(TimeControl::SuddenDeath { base: Duration::from_secs(60) }),
+    Some(TimeControl::Fischer { base: Duration::from_secs(180), increment: Duration::from_secs(2) }),
+    Some(TimeControl::SuddenDeath { base: Duration::from_secs(300) }),
+    Some(TimeControl::Bronstein { base: Duration::from_secs(300), delay: Duration::from_secs(3) }),
+];
+
+const OPPONENT_KINDS: [OpponentKind; 3] = [OpponentKind::Human, OpponentKind::Computer, OpponentKind::Network];
+const COLOURS: [Owner; 2] = [Owner::White, Owner::Black];
+
+pub fn option_rect(x: f32, y: f32, width: f32) -> Rectangle {
+    Rectangle { x, y, width, height: OPTION_HEIGHT }
+}
+
+fn bottom_button(x: f32, text: &str) -> Button {
+    Button::large(Rectangle { x, y: 400.0, width: BUTTON_WIDTH, height: BUTTON_HEIGHT }, text)
+}
+
+fn update_options(d: &mut RaylibDrawHandle, main_menu: &mut MainMenu) {
+    main_menu.opponent_label.draw(d);
+    let selected = OPPONENT_KINDS.iter().position(|&kind| kind == main_menu.opponent_kind).unwrap_or(0);
+    if let Some(i) = update_choices(d, &mut main_menu.focus, &mut main_menu.opponent_buttons, selected) {
+        main_menu.opponent_kind = OPPONENT_KINDS[i];
+    }
+
+    // Whoever joins a network game plays the host's clock. The list is drawn
+    // last of all, to open over the widgets underneath it.
+    if !main_menu.joining() {
+        main_menu.clock_label.draw(d);
+        main_menu.focus.update(d, &mut main_menu.time_controls);
+    }
+
+    match main_menu.opponent_kind {
+        OpponentKind::Human => {}
+        OpponentKind::Computer => {
+            main_menu.difficulty_label.draw(d);
+            let selected = Difficulty::ALL.iter().position(|&difficulty| difficulty == main_menu.difficulty).unwrap_or(0);
+            if let Some(i) = update_choices(d, &mut main_menu.focus, &mut main_menu.difficulty_buttons, selected) {
+                main_menu.difficulty = Difficulty::ALL[i];
+            }
+            update_colour_options(d, main_menu);
         }
+        OpponentKind::Network => {
+            main_menu.network_label.draw(d);
+            if let Some(i) = update_choices(d, &mut main_menu.focus, &mut main_menu.role_buttons, main_menu.joining as usize) {
+                main_menu.joining = i == 1;
+            }
+
+            // The host picks the colours
+            if !main_menu.joining {
+                update_colour_options(d, main_menu);
+            }
+
+            // The host only needs the port, anyone joining the host's address too
+            main_menu.address_label.draw(d);
+            main_menu.focus.update(d, &mut main_menu.address);
+            main_menu.address.draw(d);
+        }
+    }
+}
+
+fn update_colour_options(d: &mut RaylibDrawHandle, main_menu: &mut MainMenu) {
+    main_menu.colour_label.draw(d);
+    let selected = COLOURS.iter().position(|&colour| colour == main_menu.player_colour).unwrap_or(0);
+    if let Some(i) = update_choices(d, &mut main_menu.focus, &mut main_menu.colour_buttons, selected) {
+        main_menu.player_colour = COLOURS[i];
+    }
+}
+
+pub fn create_menu(d: &mut RaylibDrawHandle, game_state: &mut GameState, main_menu: &mut MainMenu) {
+    main_menu.focus.begin(d);
+    main_menu.panel.draw(d);
+    update_options(d, main_menu);
+
+    let focus = &mut main_menu.focus;
+    if focus.update(d, &mut main_menu.new_game) {
+        *game_state = GameState::Playing;
+        main_menu.analysing = false;
+        main_menu.error = None;
+    }
+    // A board to move both sides on freely, with the engine looking on
+    if focus.update(d, &mut main_menu.analysis) {
+        *game_state = GameState::Playing;
+        main_menu.analysing = true;
+        main_menu.error = None;
     }
+    // Set up a position by hand, to play or analyse from
+    if focus.update(d, &mut main_menu.editor) {
+        *game_state = GameState::Editor;
+        main_menu.error = None;
+    }
+    if focus.update(d, &mut main_menu.settings) {
+        *game_state = GameState::Settings;
+    }
+    for button in [&main_menu.new_game, &main_menu.analysis, &main_menu.editor, &main_menu.settings] {
+        button.draw(d);
     }
 
-    d.draw_rectangle((960 / 2) - (MENU_WIDTH/2), 540/2 - (MENU_HEIGHT/2), MENU_WIDTH, MENU_HEIGHT, Color::SKYBLUE);
-    d.draw_text("Rusty Chess", MENU_WIDTH/2 - 90, (960 / 2) - (MENU_WIDTH/2), 75, Color::BLACK);
+    if let Some(error) = &main_menu.error {
+        Label::new(error, 170, 468, 20, Color::RED).draw(d);
+    }
 
-    d.draw_rectangle(240, 400, 200, 60, Color::GRAY);
-    d.draw_rectangle(500, 400, 200, 60, Color::GRAY);
+    if !main_menu.joining() {
+        main_menu.time_controls.draw(d);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum OpponentKind {
+    Human,
+    Computer,
+    Network,
 }
 
+// Which end of a network game this one is
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NetworkRole {
+    Host,
+    Join,
+}
+
+// The choices made on the main menu, kept between games, and the widgets
+// they are made with
 pub struct MainMenu {
-    
+    opponent_kind: OpponentKind,
+    difficulty: Difficulty,
+    // The side the person at the board plays against the computer or over the network
+    player_colour: Owner,
+    // Joining someone else's network game rather than hosting one
+    joining: bool,
+    address: TextBox,
+    // Analysis was picked rather than a new game
+    analysing: bool,
+    // Why the last game couldn't be started
+    pub error: Option<String>,
+
+    focus: Focus,
+    panel: Panel,
+    opponent_label: Label,
+    clock_label: Label,
+    difficulty_label: Label,
+    network_label: Label,
+    colour_label: Label,
+    address_label: Label,
+    opponent_buttons: Vec<Button>,
+    time_controls: Dropdown,
+    difficulty_buttons: Vec<Button>,
+    role_buttons: Vec<Button>,
+    colour_buttons: Vec<Button>,
+    new_game: Button,
+    analysis: Button,
+    editor: Button,
+    settings: Button,
+}
+
+impl MainMenu {
+    pub fn new() -> MainMenu {
+        let mut address = TextBox::new(ADDRESS_RECT, "Host address, e.g. 192.168.1.2:7878", 64);
+        address.text = format!("127.0.0.1:{}", DEFAULT_PORT);
+        let time_controls = TIME_CONTROLS.iter().map(|control| control.map_or("None".to_string(), |control| control.to_string())).collect();
+        let difficulties: Vec<&str> = Difficulty::ALL.iter().map(|difficulty| difficulty.name()).collect();
+
+        MainMenu {
+            opponent_kind: OpponentKind::Human,
+            difficulty: Difficulty::Medium,
+            player_colour: Owner::White,
+            joining: false,
+            address,
+            analysing: false,
+            error: None,
+
+            focus: Focus::new(),
+            panel: Panel::new(PANEL_RECT, Color::SKYBLUE, "Rusty Chess", 75),
+            opponent_label: Label::new("Opponent", 170, 160, 24, Color::BLACK),
+            clock_label: Label::new("Clock", 170, 210, 24, Color::BLACK),
+            difficulty_label: Label::new("Difficulty", 170, 260, 24, Color::BLACK),
+            network_label: Label::new("Network", 170, 260, 24, Color::BLACK),
+            colour_label: Label::new("Play as", 170, 310, 24, Color::BLACK),
+            address_label: Label::new("Address", 170, 360, 24, Color::BLACK),
+            opponent_buttons: choice_row(option_rect(330.0, 150.0, 130.0), 10.0, &["Human", "Computer", "Network"]),
+            time_controls: Dropdown::new(option_rect(330.0, 200.0, 200.0), time_controls, 0),
+            difficulty_buttons: choice_row(option_rect(330.0, 250.0, 130.0), 10.0, &difficulties),
+            role_buttons: choice_row(option_rect(330.0, 250.0, 200.0), 10.0, &["Host", "Join"]),
+            colour_buttons: choice_row(option_rect(330.0, 300.0, 200.0), 10.0, &["White", "Black"]),
+            new_game: bottom_button(170.0, "New game"),
+            analysis: bottom_button(330.0, "Analysis"),
+            editor: bottom_button(490.0, "Editor"),
+            settings: bottom_button(650.0, "Settings"),
+        }
+    }
 
+    fn joining(&self) -> bool {
+        self.opponent_kind == OpponentKind::Network && self.joining
+    }
+
+    // Someone joining finds out who they are playing once the host's game arrives
+    pub fn opponent(&self) -> Opponent {
+        match self.opponent_kind {
+            OpponentKind::Computer => Opponent::Computer { colour: self.player_colour.opponent(), difficulty: self.difficulty },
+            OpponentKind::Network if !self.joining => Opponent::Remote { colour: self.player_colour.opponent() },
+            _ => Opponent::Human,
+        }
+    }
+
+    pub fn time_control(&self) -> Option<TimeControl> {
+        if self.joining() { None } else { TIME_CONTROLS[self.time_controls.selected] }
+    }
+
+    pub fn analysis(&self) -> bool {
+        self.analysing
+    }
+
+    // Analysis is never over the network
+    pub fn network(&self) -> Option<(NetworkRole, &str)> {
+        if self.analysing {
+            return None;
+        }
+        match self.opponent_kind {
+            OpponentKind::Network if self.joining => Some((NetworkRole::Join, &self.address.text)),
+            OpponentKind::Network => Some((NetworkRole::Host, &self.address.text)),
+            _ => None,
+        }
+    }
 }
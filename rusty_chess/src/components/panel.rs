@@ -0,0 +1,22 @@
+use raylib::prelude::*;
+
+// The coloured box a screen's widgets sit on, with a title across the top
+pub struct Panel {
+    rect: Rectangle,
+    color: Color,
+    pub title: String,
+    title_size: i32,
+}
+
+impl Panel {
+    pub fn new(rect: Rectangle, color: Color, title: &str, title_size: i32) -> Panel {
+        Panel { rect, color, title: title.to_string(), title_size }
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        d.draw_rectangle_rec(self.rect, self.color);
+        let title_width = d.measure_text(&self.title, self.title_size);
+        let x = (self.rect.x + self.rect.width / 2.0) as i32 - title_width / 2;
+        d.draw_text(&self.title, x, self.rect.y as i32 + 15, self.title_size, Color::BLACK);
+    }
+}
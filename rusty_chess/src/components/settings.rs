@@ -0,0 +1,139 @@
+use raylib::prelude::*;
+use crate::components::button::Button;
+use crate::components::focus::Focus;
+use crate::components::label::Label;
+use crate::components::menu::{option_rect, PANEL_RECT};
+use crate::components::panel::Panel;
+use crate::components::widget::Widget;
+use crate::game::GameState;
+use crate::theme::{available_themes, PieceSet};
+use rusty_chess::board::piece::{Owner, Piece, PieceType};
+use std::path::PathBuf;
+
+const BACK_RECT: Rectangle = Rectangle { x: 380.0, y: 420.0, width: 200.0, height: 60.0 };
+
+// Themes are listed down the left, the one in use shown on the right
+const THEME_LIST_X: f32 = 170.0;
+const THEME_LIST_Y: f32 = 150.0;
+const THEME_ROWS: usize = 4;
+// Under the list, for paging through it
+const PAGE_Y: f32 = THEME_LIST_Y + THEME_ROWS as f32 * 45.0;
+const PAGE_BUTTON_WIDTH: f32 = 60.0;
+
+pub struct Settings {
+    pub themes_dir: PathBuf,
+    // Every theme in the directory when the screen was opened
+    names: Vec<String>,
+    // Which THEME_ROWS of them are listed
+    page: usize,
+    // One button per theme on the page, named after it
+    themes: Vec<Button>,
+    // Why the last theme picked couldn't be used
+    pub error: Option<String>,
+
+    focus: Focus,
+    panel: Panel,
+    pieces_label: Label,
+    previous_page: Button,
+    next_page: Button,
+    back: Button,
+}
+
+impl Settings {
+    pub fn new(themes_dir: PathBuf) -> Settings {
+        let mut settings = Settings {
+            themes_dir,
+            names: Vec::new(),
+            page: 0,
+            themes: Vec::new(),
+            error: None,
+            focus: Focus::new(),
+            panel: Panel::new(PANEL_RECT, Color::SKYBLUE, "Settings", 50),
+            pieces_label: Label::new("Pieces", THEME_LIST_X as i32, THEME_LIST_Y as i32 - 30, 24, Color::BLACK),
+            previous_page: Button::new(option_rect(THEME_LIST_X, PAGE_Y, PAGE_BUTTON_WIDTH), "<"),
+            next_page: Button::new(option_rect(THEME_LIST_X + 280.0 - PAGE_BUTTON_WIDTH, PAGE_Y, PAGE_BUTTON_WIDTH), ">"),
+            back: Button::large(BACK_RECT, "Back"),
+        };
+        settings.refresh();
+        settings
+    }
+
+    // Looks through the directory again, for themes added or taken away since
+    pub fn refresh(&mut self) {
+        self.names = available_themes(&self.themes_dir);
+        self.show_page(self.page.min(self.pages() - 1));
+    }
+
+    fn pages(&self) -> usize {
+        self.names.len().div_ceil(THEME_ROWS).max(1)
+    }
+
+    fn show_page(&mut self, page: usize) {
+        self.page = page;
+        self.themes = self.names
+            .iter()
+            .skip(page * THEME_ROWS)
+            .take(THEME_ROWS)
+            .enumerate()
+            .map(|(i, theme)| Button::new(option_rect(THEME_LIST_X, THEME_LIST_Y + i as f32 * 45.0, 280.0), theme))
+            .collect();
+        self.previous_page.enabled = page > 0;
+        self.next_page.enabled = page + 1 < self.pages();
+    }
+}
+
+// Returns the theme picked this frame, for the caller to load
+pub fn create_settings(d: &mut RaylibDrawHandle, game_state: &mut GameState, settings: &mut Settings, pieces: &PieceSet) -> Option<String> {
+    settings.focus.begin(d);
+    settings.panel.draw(d);
+    settings.pieces_label.draw(d);
+
+    let mut picked = None;
+    for button in &mut settings.themes {
+        if settings.focus.update(d, button) && button.text != pieces.name() {
+            picked = Some(button.text.clone());
+        }
+        button.selected = button.text == pieces.name();
+        button.draw(d);
+    }
+    if settings.themes.is_empty() {
+        let text = format!("No themes in {}", settings.themes_dir.display());
+        Label::new(&text, THEME_LIST_X as i32, THEME_LIST_Y as i32, 20, Color::DARKGRAY).draw(d);
+    }
+
+    // Only needed once there are more themes than fit
+    if settings.pages() > 1 {
+        if settings.focus.update(d, &mut settings.previous_page) {
+            settings.show_page(settings.page - 1);
+        }
+        if settings.focus.update(d, &mut settings.next_page) {
+            settings.show_page(settings.page + 1);
+        }
+        settings.previous_page.draw(d);
+        settings.next_page.draw(d);
+        let text = format!("{} of {}", settings.page + 1, settings.pages());
+        let x = THEME_LIST_X as i32 + 140 - d.measure_text(&text, 20) / 2;
+        Label::new(&text, x, PAGE_Y as i32 + 10, 20, Color::BLACK).draw(d);
+    }
+
+    // A preview of the set in use
+    for (i, piece_type) in [PieceType::King, PieceType::Queen, PieceType::Knight].into_iter().enumerate() {
+        for (j, owner) in [Owner::White, Owner::Black].into_iter().enumerate() {
+            let dest = Rectangle { x: 500.0 + i as f32 * 90.0, y: 150.0 + j as f32 * 90.0, width: 80.0, height: 80.0 };
+            d.draw_rectangle_rec(dest, if (i + j) % 2 == 0 { Color::LIGHTGRAY } else { Color::GRAY });
+            pieces.draw(d, Piece::new(owner, piece_type), dest);
+        }
+    }
+
+    if let Some(error) = &settings.error {
+        Label::new(error, THEME_LIST_X as i32, 385, 20, Color::RED).draw(d);
+    }
+
+    if settings.focus.update(d, &mut settings.back) {
+        settings.error = None;
+        *game_state = GameState::Menu;
+    }
+    settings.back.draw(d);
+
+    picked
+}
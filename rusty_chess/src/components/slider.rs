@@ -0,0 +1,101 @@
+use raylib::prelude::*;
+use raylib::consts::KeyboardKey::*;
+use raylib::consts::MouseButton::*;
+use crate::components::widget::{draw_focus, Widget, FONT_SIZE};
+
+const KNOB_WIDTH: f32 = 12.0;
+// Room to the right of the track for the value
+const VALUE_WIDTH: f32 = 50.0;
+
+// A whole number from `min` to `max`, picked by dragging the knob along the
+// track or with Left and Right while it has the keyboard
+pub struct Slider {
+    rect: Rectangle,
+    min: i32,
+    max: i32,
+    pub value: i32,
+    dragging: bool,
+    focused: bool,
+}
+
+impl Slider {
+    pub fn new(rect: Rectangle, min: i32, max: i32, value: i32) -> Slider {
+        Slider { rect, min, max, value: value.clamp(min, max), dragging: false, focused: false }
+    }
+
+    fn track(&self) -> Rectangle {
+        Rectangle { width: self.rect.width - VALUE_WIDTH, ..self.rect }
+    }
+
+    // How far the knob's left edge moves from one end of the track to the other
+    fn travel(&self) -> f32 {
+        self.track().width - KNOB_WIDTH
+    }
+
+    // The value with the middle of the knob at `x`, over the same span it is drawn along
+    fn value_at(&self, x: f32) -> i32 {
+        let track = self.track();
+        let fraction = ((x - track.x - KNOB_WIDTH / 2.0) / self.travel()).clamp(0.0, 1.0);
+        self.min + (fraction * (self.max - self.min) as f32).round() as i32
+    }
+}
+
+impl Widget for Slider {
+    fn rect(&self) -> Rectangle {
+        self.rect
+    }
+
+    fn focused(&self) -> bool {
+        self.focused
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn update(&mut self, d: &mut RaylibDrawHandle, hovered: bool) -> bool {
+        let old = self.value;
+        if hovered && d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+            self.dragging = true;
+        }
+        if !d.is_mouse_button_down(MOUSE_BUTTON_LEFT) {
+            self.dragging = false;
+        }
+        // The knob keeps following the mouse when it wanders off the track
+        if self.dragging {
+            self.value = self.value_at(d.get_mouse_position().x);
+        }
+
+        if self.focused {
+            let step = |key| d.is_key_pressed(key) || d.is_key_pressed_repeat(key);
+            if step(KEY_LEFT) {
+                self.value = (self.value - 1).max(self.min);
+            } else if step(KEY_RIGHT) {
+                self.value = (self.value + 1).min(self.max);
+            }
+        }
+        self.value != old
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle) {
+        let track = self.track();
+        let middle = track.y + track.height / 2.0;
+        d.draw_rectangle_rec(Rectangle { x: track.x, y: middle - 3.0, width: track.width, height: 6.0 }, Color::GRAY);
+
+        let fraction = if self.max > self.min { (self.value - self.min) as f32 / (self.max - self.min) as f32 } else { 0.0 };
+        let knob = Rectangle {
+            x: track.x + fraction * self.travel(),
+            y: track.y,
+            width: KNOB_WIDTH,
+            height: track.height,
+        };
+        d.draw_rectangle_rec(knob, if self.dragging { Color::BLACK } else { Color::DARKGRAY });
+
+        let x = (track.x + track.width) as i32 + 10;
+        let y = middle as i32 - FONT_SIZE / 2;
+        d.draw_text(&self.value.to_string(), x, y, FONT_SIZE, Color::BLACK);
+        if self.focused {
+            draw_focus(d, self.rect);
+        }
+    }
+}
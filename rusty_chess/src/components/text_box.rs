@@ -0,0 +1,91 @@
+use raylib::prelude::*;
+use raylib::consts::KeyboardKey::*;
+use crate::components::widget::{Widget, FONT_SIZE};
+
+// A single line of text typed in by the player. It takes the keyboard while
+// it has focus, which clicking it or pressing Tab gives it.
+pub struct TextBox {
+    rect: Rectangle,
+    placeholder: &'static str,
+    max_len: usize,
+    pub text: String,
+    // Shown at the right end of the box, e.g. why the text was turned down
+    pub error: Option<&'static str>,
+    focused: bool,
+}
+
+impl TextBox {
+    pub fn new(rect: Rectangle, placeholder: &'static str, max_len: usize) -> TextBox {
+        TextBox {
+            rect,
+            placeholder,
+            max_len,
+            text: String::new(),
+            error: None,
+            focused: false,
+        }
+    }
+}
+
+impl Widget for TextBox {
+    fn rect(&self) -> Rectangle {
+        self.rect
+    }
+
+    fn focused(&self) -> bool {
+        self.focused
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    // Handle this frame's typing. Returns whether Enter was pressed, leaving
+    // the text in the box for the caller to clear if it was accepted.
+    fn update(&mut self, d: &mut RaylibDrawHandle, _hovered: bool) -> bool {
+        if !self.focused {
+            return false;
+        }
+
+        while let Some(c) = d.get_char_pressed() {
+            if c.is_ascii_graphic() && self.text.len() < self.max_len {
+                self.text.push(c);
+            }
+        }
+        if d.is_key_pressed(KEY_BACKSPACE) || d.is_key_pressed_repeat(KEY_BACKSPACE) {
+            self.text.pop();
+        }
+
+        if d.is_key_pressed(KEY_ESCAPE) {
+            self.text.clear();
+            self.focused = false;
+            return false;
+        }
+        d.is_key_pressed(KEY_ENTER) && !self.text.is_empty()
+    }
+
+    fn draw(&self, d: &mut RaylibDrawHandle) {
+        d.draw_rectangle_rec(self.rect, Color::RAYWHITE);
+        let border = if self.focused { Color::DARKGRAY } else { Color::LIGHTGRAY };
+        d.draw_rectangle_lines_ex(self.rect, 2.0, border);
+
+        let x = self.rect.x as i32 + 8;
+        let y = (self.rect.y + self.rect.height / 2.0) as i32 - FONT_SIZE / 2;
+        if self.text.is_empty() && !self.focused {
+            d.draw_text(self.placeholder, x, y, FONT_SIZE, Color::GRAY);
+        } else {
+            d.draw_text(&self.text, x, y, FONT_SIZE, Color::BLACK);
+        }
+
+        // A caret after the text, blinking twice a second
+        if self.focused && (d.get_time() * 2.0) as i64 % 2 == 0 {
+            let caret_x = x + d.measure_text(&self.text, FONT_SIZE) + 2;
+            d.draw_rectangle(caret_x, y, 2, FONT_SIZE, Color::BLACK);
+        }
+
+        if let Some(error) = self.error {
+            let text_width = d.measure_text(error, FONT_SIZE);
+            d.draw_text(error, (self.rect.x + self.rect.width) as i32 - text_width - 8, y, FONT_SIZE, Color::RED);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use raylib::prelude::*;
+
+// Everything is laid out for a window of this size, then scaled to fit the real one
+pub const LAYOUT_WIDTH: f32 = 960.0;
+pub const LAYOUT_HEIGHT: f32 = 540.0;
+
+// Where the layout ends up in the window: scaled evenly to fill as much of it
+// as possible, and centred with bars along the sides that are left over
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    pub scale: f32,
+    pub offset: Vector2,
+}
+
+impl Viewport {
+    pub fn fit(screen_width: i32, screen_height: i32) -> Viewport {
+        let scale = (screen_width as f32 / LAYOUT_WIDTH).min(screen_height as f32 / LAYOUT_HEIGHT).max(0.1);
+        Viewport {
+            scale,
+            offset: Vector2 {
+                x: ((screen_width as f32 - LAYOUT_WIDTH * scale) / 2.0).floor(),
+                y: ((screen_height as f32 - LAYOUT_HEIGHT * scale) / 2.0).floor(),
+            },
+        }
+    }
+
+    // Drawing through this camera takes layout coordinates
+    pub fn camera(&self) -> Camera2D {
+        Camera2D {
+            offset: self.offset,
+            target: Vector2 { x: 0.0, y: 0.0 },
+            rotation: 0.0,
+            zoom: self.scale,
+        }
+    }
+
+    // Have raylib report the mouse in layout coordinates too, so hit-testing
+    // doesn't need to know about the scaling
+    pub fn apply_to_mouse(&self, rl: &mut RaylibHandle) {
+        rl.set_mouse_offset(Vector2 { x: -self.offset.x, y: -self.offset.y });
+        rl.set_mouse_scale(1.0 / self.scale, 1.0 / self.scale);
+    }
+}
@@ -0,0 +1,47 @@
+use raylib::prelude::*;
+use raylib::consts::KeyboardKey::*;
+
+pub const FONT_SIZE: i32 = 20;
+
+const FOCUS_COLOR: Color = Color { r: 0, g: 80, b: 180, a: 255 };
+
+// Something on a screen that takes the mouse or the keyboard. Screens keep
+// their widgets from frame to frame and hand each one to `Focus::update`,
+// which decides whether it has the keyboard and whether the mouse can reach it.
+pub trait Widget {
+    fn rect(&self) -> Rectangle;
+
+    // Space it covers beyond its rect for now, like an open dropdown's list
+    fn overlay(&self) -> Option<Rectangle> {
+        None
+    }
+
+    fn focused(&self) -> bool;
+
+    fn set_focused(&mut self, focused: bool);
+
+    // Handle this frame's input, `hovered` when the mouse is over it and not
+    // over something covering it. Returns whether it was used: clicked,
+    // toggled, given a new value or, for a text box, had Enter pressed.
+    fn update(&mut self, d: &mut RaylibDrawHandle, hovered: bool) -> bool;
+
+    fn draw(&self, d: &mut RaylibDrawHandle);
+}
+
+// Enter or Space does for a widget with the keyboard what a click would
+pub fn activated(d: &RaylibDrawHandle, focused: bool) -> bool {
+    focused && (d.is_key_pressed(KEY_ENTER) || d.is_key_pressed(KEY_SPACE))
+}
+
+pub fn draw_focus(d: &mut RaylibDrawHandle, rect: Rectangle) {
+    let ring = Rectangle { x: rect.x - 3.0, y: rect.y - 3.0, width: rect.width + 6.0, height: rect.height + 6.0 };
+    d.draw_rectangle_lines_ex(ring, 2.0, FOCUS_COLOR);
+}
+
+// `text` drawn in the middle of `rect`
+pub fn draw_centered_text(d: &mut RaylibDrawHandle, rect: Rectangle, text: &str, font_size: i32, color: Color) {
+    let text_width = d.measure_text(text, font_size);
+    let x = (rect.x + rect.width / 2.0) as i32 - text_width / 2;
+    let y = (rect.y + rect.height / 2.0) as i32 - font_size / 2;
+    d.draw_text(text, x, y, font_size, color);
+}
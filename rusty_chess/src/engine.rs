@@ -0,0 +1,6 @@
+pub mod book;
+pub mod eval;
+pub mod search;
+pub mod syzygy;
+pub mod tt;
+pub mod uci;
@@ -0,0 +1,170 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::board::movegen::Move;
+use crate::board::piece::PieceType;
+use crate::board::position::Position;
+use crate::board::square::Square;
+
+// A Polyglot entry is the position's key, the move, its weight and four
+// bytes of learning data nothing uses, all big-endian
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Entry {
+    key: u64,
+    mv: u16,
+    weight: u16,
+}
+
+// An opening book in the Polyglot .bin format: moves for known positions,
+// each weighted by how good or how popular it is
+pub struct Book {
+    // Sorted by key, as the format requires
+    entries: Vec<Entry>,
+}
+
+impl Book {
+    pub fn open(path: &Path) -> io::Result<Book> {
+        Ok(Book::from_bytes(&fs::read(path)?))
+    }
+
+    // A truncated entry at the end is left out
+    pub fn from_bytes(bytes: &[u8]) -> Book {
+        let mut entries: Vec<Entry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| Entry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes(entry[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(entry[10..12].try_into().unwrap()),
+            })
+            .collect();
+        // Books are sorted already, but looking things up relies on it
+        entries.sort_by_key(|entry| entry.key);
+        Book { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // The book's moves for the position and their weights, leaving out any
+    // that aren't legal there (which happens when two positions share a key)
+    pub fn moves(&self, position: &Position) -> Vec<(Move, u16)> {
+        let key = position.polyglot_key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| decode_move(position, entry.mv).map(|mv| (mv, entry.weight)))
+            .filter(|&(mv, _)| position.is_legal(mv))
+            .collect()
+    }
+
+    // One of the book's moves, chosen with odds in proportion to their weights.
+    // `random` is any random number, it is reduced to the total weight here.
+    pub fn choose(&self, position: &Position, random: u64) -> Option<Move> {
+        let moves = self.moves(position);
+        let total: u64 = moves.iter().map(|&(_, weight)| weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = random % total;
+        for (mv, weight) in moves {
+            if roll < weight as u64 {
+                return Some(mv);
+            }
+            roll -= weight as u64;
+        }
+        None
+    }
+}
+
+// Polyglot packs a move into 16 bits: the to file and rank, the from file
+// and rank (three bits each, counting ranks up from white's side) and what a
+// pawn promotes to. Castling is written as the king taking its own rook.
+fn decode_move(position: &Position, bits: u16) -> Option<Move> {
+    let square = |file: u16, rank: u16| Square::new(file as usize, 7 - rank as usize);
+    let from = square((bits >> 6) & 7, (bits >> 9) & 7);
+    let mut to = square(bits & 7, (bits >> 3) & 7);
+
+    let promotion = match (bits >> 12) & 7 {
+        0 => None,
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        4 => Some(PieceType::Queen),
+        _ => return None,
+    };
+
+    let piece = position.piece_at(from)?;
+    let castles = piece.piece_type == PieceType::King
+        && position.piece_at(to).is_some_and(|target| target.owner == piece.owner && target.piece_type == PieceType::Rook);
+    if castles {
+        let col = if to.col() > from.col() { 6 } else { 2 };
+        to = Square::new(col, from.row());
+    }
+
+    Some(match promotion {
+        Some(piece_type) => Move::with_promotion(from, to, piece_type),
+        None => Move::new(from, to),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(key: u64, uci: &str, weight: u16) -> Vec<u8> {
+        let mv = Move::from_uci(uci).unwrap();
+        let square = |square: Square| (square.col() | (7 - square.row()) << 3) as u16;
+        let promotion = match mv.promotion {
+            Some(PieceType::Queen) => 4,
+            _ => 0,
+        };
+        let bits = square(mv.to) | square(mv.from) << 6 | promotion << 12;
+
+        let mut bytes = key.to_be_bytes().to_vec();
+        bytes.extend(bits.to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend([0; 4]);
+        bytes
+    }
+
+    #[test]
+    fn test_moves_and_weights() {
+        let start = Position::default();
+        let other = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut bytes = entry(start.polyglot_key(), "e2e4", 30);
+        bytes.extend(entry(other.polyglot_key(), "e1e2", 5));
+        bytes.extend(entry(start.polyglot_key(), "d2d4", 10));
+        let book = Book::from_bytes(&bytes);
+
+        assert_eq!(book.len(), 3);
+        let mut moves = book.moves(&start);
+        moves.sort_by_key(|&(_, weight)| weight);
+        assert_eq!(moves, vec![(Move::from_uci("d2d4").unwrap(), 10), (Move::from_uci("e2e4").unwrap(), 30)]);
+
+        // In book order e4 takes the first 30 of the 40 possible rolls, d4 the last 10
+        let e4 = Move::from_uci("e2e4").unwrap();
+        let d4 = Move::from_uci("d2d4").unwrap();
+        let chosen: Vec<Move> = [0, 29, 30, 39, 40].iter().map(|&random| book.choose(&start, random).unwrap()).collect();
+        assert_eq!(chosen, vec![e4, e4, d4, d4, e4]);
+        assert!(book.choose(&Position::from_fen("4k3/8/8/8/8/8/4K3/8 b - - 0 1").unwrap(), 0).is_none());
+    }
+
+    #[test]
+    fn test_castling_is_decoded() {
+        let position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let book = Book::from_bytes(&entry(position.polyglot_key(), "e1h1", 1));
+
+        assert_eq!(book.choose(&position, 0), Some(Move::from_uci("e1g1").unwrap()));
+    }
+}
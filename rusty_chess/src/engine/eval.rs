@@ -0,0 +1,161 @@
+use crate::board::bitboard::squares;
+use crate::board::piece::{Owner, PieceType};
+use crate::board::position::Position;
+
+// Piece-square tables from white's side of the board, indexed `[row][column]`
+// like the board itself, so the first row is rank 8. Black reads them upside down.
+type Table = [[i32; 8]; 8];
+
+const PAWN_TABLE: Table = [
+    [0, 0, 0, 0, 0, 0, 0, 0],
+    [50, 50, 50, 50, 50, 50, 50, 50],
+    [10, 10, 20, 30, 30, 20, 10, 10],
+    [5, 5, 10, 25, 25, 10, 5, 5],
+    [0, 0, 0, 20, 20, 0, 0, 0],
+    [5, -5, -10, 0, 0, -10, -5, 5],
+    [5, 10, 10, -20, -20, 10, 10, 5],
+    [0, 0, 0, 0, 0, 0, 0, 0],
+];
+
+const KNIGHT_TABLE: Table = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20, 0, 0, 0, 0, -20, -40],
+    [-30, 0, 10, 15, 15, 10, 0, -30],
+    [-30, 5, 15, 20, 20, 15, 5, -30],
+    [-30, 0, 15, 20, 20, 15, 0, -30],
+    [-30, 5, 10, 15, 15, 10, 5, -30],
+    [-40, -20, 0, 5, 5, 0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+
+const BISHOP_TABLE: Table = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10, 0, 0, 0, 0, 0, 0, -10],
+    [-10, 0, 5, 10, 10, 5, 0, -10],
+    [-10, 5, 5, 10, 10, 5, 5, -10],
+    [-10, 0, 10, 10, 10, 10, 0, -10],
+    [-10, 10, 10, 10, 10, 10, 10, -10],
+    [-10, 5, 0, 0, 0, 0, 5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+
+const ROOK_TABLE: Table = [
+    [0, 0, 0, 0, 0, 0, 0, 0],
+    [5, 10, 10, 10, 10, 10, 10, 5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [0, 0, 0, 5, 5, 0, 0, 0],
+];
+
+const QUEEN_TABLE: Table = [
+    [-20, -10, -10, -5, -5, -10, -10, -20],
+    [-10, 0, 0, 0, 0, 0, 0, -10],
+    [-10, 0, 5, 5, 5, 5, 0, -10],
+    [-5, 0, 5, 5, 5, 5, 0, -5],
+    [0, 0, 5, 5, 5, 5, 0, -5],
+    [-10, 5, 5, 5, 5, 5, 0, -10],
+    [-10, 0, 5, 0, 0, 0, 0, -10],
+    [-20, -10, -10, -5, -5, -10, -10, -20],
+];
+
+// The king hides behind its pawns while there is still material to attack it...
+const KING_MIDDLE_GAME_TABLE: Table = [
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [20, 20, 0, 0, 0, 0, 20, 20],
+    [20, 30, 10, 0, 0, 10, 30, 20],
+];
+
+// ...and heads for the centre once the board has cleared
+const KING_END_GAME_TABLE: Table = [
+    [-50, -40, -30, -20, -20, -30, -40, -50],
+    [-30, -20, -10, 0, 0, -10, -20, -30],
+    [-30, -10, 20, 30, 30, 20, -10, -30],
+    [-30, -10, 30, 40, 40, 30, -10, -30],
+    [-30, -10, 30, 40, 40, 30, -10, -30],
+    [-30, -10, 20, 30, 30, 20, -10, -30],
+    [-30, -30, 0, 0, 0, 0, -30, -30],
+    [-50, -30, -30, -30, -30, -30, -30, -50],
+];
+
+// Pieces other than pawns and kings worth less than this in total count as an end game
+const END_GAME_MATERIAL: i32 = 1_300;
+
+// Material value in centipawns. The king is never traded, so it has none.
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 0,
+    }
+}
+
+fn is_end_game(position: &Position) -> bool {
+    let mut material = 0;
+    for owner in [Owner::Black, Owner::White] {
+        for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            material += position.bitboard(owner, piece_type).count_ones() as i32 * piece_value(piece_type);
+        }
+    }
+    material <= END_GAME_MATERIAL
+}
+
+// Static score of the position in centipawns, from the side to move's point of view
+pub fn evaluate(position: &Position) -> i32 {
+    let king_table = if is_end_game(position) { &KING_END_GAME_TABLE } else { &KING_MIDDLE_GAME_TABLE };
+
+    let mut score = 0;
+    for owner in [Owner::Black, Owner::White] {
+        for piece_type in PieceType::ALL {
+            let table = match piece_type {
+                PieceType::Pawn => &PAWN_TABLE,
+                PieceType::Knight => &KNIGHT_TABLE,
+                PieceType::Bishop => &BISHOP_TABLE,
+                PieceType::Rook => &ROOK_TABLE,
+                PieceType::Queen => &QUEEN_TABLE,
+                PieceType::King => king_table,
+            };
+
+            for square in squares(position.bitboard(owner, piece_type)) {
+                let table_row = match owner {
+                    Owner::White => square.row(),
+                    Owner::Black => 7 - square.row(),
+                };
+
+                let value = piece_value(piece_type) + table[table_row][square.col()];
+                if owner == position.turn {
+                    score += value;
+                } else {
+                    score -= value;
+                }
+            }
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_evaluation_is_symmetric() {
+        assert_eq!(evaluate(&Position::default()), 0);
+
+        // The same position with the colours swapped scores the same for the side to move
+        let white = Position::from_fen("4k3/8/8/3q4/8/2N5/PP6/4K3 w - - 0 1").unwrap();
+        let black = Position::from_fen("4k3/pp6/2n5/8/3Q4/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(evaluate(&white), evaluate(&black));
+        assert!(evaluate(&white) < 0);
+    }
+}
@@ -0,0 +1,508 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::movegen::Move;
+use crate::board::piece::PieceType;
+use crate::board::position::Position;
+use crate::engine::eval::{evaluate, piece_value};
+use crate::engine::syzygy::Tablebases;
+use crate::engine::tt::{Bound, Entry, TranspositionTable};
+
+// Scores above this are forced mates, counted down by the plies needed to deliver them
+pub const MATE_SCORE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+const MAX_DEPTH: u32 = 64;
+
+// How often the search looks at the clock and the stop flag
+const NODES_BETWEEN_CHECKS: u64 = 1_024;
+
+// When to stop searching. With neither limit set the search runs until stopped.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+}
+
+// What the search found at the end of one iteration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    // Centipawns from the side to move's point of view
+    pub score: i32,
+    pub nodes: u64,
+    pub time: Duration,
+    // Principal variation: the best line found, starting with the move to play
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    // Full moves until mate, negative when the side to move is the one getting mated
+    pub fn mate_in(&self) -> Option<i32> {
+        if !is_mate_score(self.score) {
+            return None;
+        }
+        let plies = MATE_SCORE - self.score.abs();
+        let moves = (plies + 1) / 2;
+        Some(if self.score > 0 { moves } else { -moves })
+    }
+}
+
+struct Searcher<'a> {
+    stop: &'a AtomicBool,
+    table: &'a mut TranspositionTable,
+    deadline: Option<Instant>,
+    nodes: u64,
+    // The first iteration always runs to the end, so there is a move to play
+    may_stop: bool,
+    aborted: bool,
+    // Best move of the previous iteration, searched first at the root
+    root_move: Option<Move>,
+    // Root moves left out, the ones earlier lines of the same iteration start with
+    excluded: Vec<Move>,
+    // Hashes of the game so far followed by the line being searched, for spotting repetitions
+    path: Vec<u64>,
+}
+
+fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - MAX_DEPTH as i32 * 2
+}
+
+// Mate scores count plies from the root, but the table is shared between
+// searches from different roots, so it stores them counted from the position itself
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score + ply as i32
+    } else {
+        score - ply as i32
+    }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score - ply as i32
+    } else {
+        score + ply as i32
+    }
+}
+
+// Most valuable victim, least valuable attacker first, then promotions
+fn move_order_score(position: &Position, mv: Move) -> i32 {
+    let mut score = 0;
+    if position.is_capture(mv) {
+        let victim = position.piece_at(mv.to).map_or(PieceType::Pawn, |p| p.piece_type);
+        let attacker = position.piece_at(mv.from).map_or(PieceType::Pawn, |p| p.piece_type);
+        score += 10 * piece_value(victim) - piece_value(attacker) + 10_000;
+    }
+    if let Some(promotion) = mv.promotion {
+        score += piece_value(promotion);
+    }
+    score
+}
+
+fn order_moves(position: &Position, moves: &mut [Move], first: Option<Move>) {
+    moves.sort_by_key(|&mv| {
+        if Some(mv) == first {
+            i32::MIN
+        } else {
+            -move_order_score(position, mv)
+        }
+    });
+}
+
+impl Searcher<'_> {
+
+    fn should_stop(&mut self) -> bool {
+        if self.may_stop && self.nodes.is_multiple_of(NODES_BETWEEN_CHECKS) {
+            let out_of_time = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if out_of_time || self.stop.load(Ordering::Relaxed) {
+                self.aborted = true;
+            }
+        }
+        self.aborted
+    }
+
+    fn is_draw(&self, position: &Position) -> bool {
+        if position.halfmove_clock >= 100 {
+            return true;
+        }
+        // Positions before the last capture or pawn move can't come up again
+        self.path
+            .iter()
+            .rev()
+            .take(position.halfmove_clock as usize)
+            .any(|&earlier| earlier == position.hash)
+    }
+
+    fn negamax(&mut self, position: &Position, depth: u32, ply: u32, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        if ply > 0 && self.is_draw(position) {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiesce(position, alpha, beta);
+        }
+
+        // A deep enough earlier search of this position may settle it straight away.
+        // Not at the root though, that has to come up with a move.
+        let stored = self.table.probe(position.hash);
+        if let Some(entry) = stored.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            let settled = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if settled {
+                return score;
+            }
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.in_check() { -MATE_SCORE + ply as i32 } else { 0 };
+        }
+        if ply == 0 {
+            moves.retain(|mv| !self.excluded.contains(mv));
+        }
+        let first = if ply == 0 { self.root_move } else { stored.and_then(|entry| entry.best_move) };
+        order_moves(position, &mut moves, first);
+
+        let original_alpha = alpha;
+        let mut best_move = None;
+        let mut best = -INFINITY;
+        let mut line = Vec::new();
+        for mv in moves {
+            let mut child = *position;
+            child.apply_move(mv);
+
+            self.path.push(position.hash);
+            line.clear();
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            self.path.pop();
+
+            if self.aborted {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best <= original_alpha {
+            Bound::Upper
+        } else {
+            Bound::Exact
+        };
+        // With moves left out the root's score isn't its own
+        if ply > 0 || self.excluded.is_empty() {
+            self.table.store(Entry { key: position.hash, depth, score: score_to_table(best, ply), bound, best_move });
+        }
+        best
+    }
+
+    // Only look at captures and promotions, so the search never stops in the middle of an exchange
+    fn quiesce(&mut self, position: &Position, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let stand_pat = evaluate(position);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = position
+            .legal_moves()
+            .into_iter()
+            .filter(|&mv| position.is_capture(mv) || mv.promotion == Some(PieceType::Queen))
+            .collect();
+        order_moves(position, &mut moves, None);
+
+        for mv in moves {
+            let mut child = *position;
+            child.apply_move(mv);
+            let score = -self.quiesce(&child, -beta, -alpha);
+
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+// Iterative deepening alpha-beta search. `history` holds the positions before
+// each move of the game so far, and `on_info` hears about every finished iteration.
+// Setting `stop` ends the search early with the best move found so far. Results
+// are kept in `table`, which can be reused for the next search of the same game.
+pub fn search(
+    position: &Position,
+    history: &[Position],
+    limits: SearchLimits,
+    table: &mut TranspositionTable,
+    stop: &AtomicBool,
+    mut on_info: impl FnMut(&SearchInfo),
+) -> SearchInfo {
+    let lines = search_lines(position, history, limits, 1, table, stop, |lines| on_info(&lines[0]));
+    lines.into_iter().next().unwrap_or_default()
+}
+
+// Like `search`, but for the best `count` moves, each with the line it leads
+// to. Every iteration searches the root again without the moves already found,
+// and its lines are passed to `on_lines` best first.
+pub fn search_lines(
+    position: &Position,
+    history: &[Position],
+    limits: SearchLimits,
+    count: usize,
+    table: &mut TranspositionTable,
+    stop: &AtomicBool,
+    mut on_lines: impl FnMut(&[SearchInfo]),
+) -> Vec<SearchInfo> {
+    let start = Instant::now();
+    let mut searcher = Searcher {
+        stop,
+        table,
+        deadline: limits.movetime.map(|movetime| start + movetime),
+        nodes: 0,
+        may_stop: false,
+        aborted: false,
+        root_move: None,
+        excluded: Vec::new(),
+        path: history.iter().map(|earlier| earlier.hash).collect(),
+    };
+    // There is always one line, if only to say the game is over
+    let count = count.clamp(1, position.legal_moves().len().max(1));
+
+    let mut best: Vec<SearchInfo> = Vec::new();
+    // Depth 0 would search nothing and have no move to give
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH) {
+        searcher.may_stop = depth > 1;
+        searcher.excluded.clear();
+        let mut found = Vec::with_capacity(count);
+        for index in 0..count {
+            searcher.root_move = best.get(index).and_then(SearchInfo::best_move);
+            let mut pv = Vec::new();
+            let score = searcher.negamax(position, depth, 0, -INFINITY, INFINITY, &mut pv);
+            if searcher.aborted {
+                break;
+            }
+            searcher.excluded.extend(pv.first());
+            found.push(SearchInfo { depth, score, nodes: searcher.nodes, time: start.elapsed(), pv });
+        }
+
+        if searcher.aborted {
+            break;
+        }
+        // A later line can come out ahead of an earlier one the search cut short
+        found.sort_by_key(|info| -info.score);
+        best = found;
+        on_lines(&best);
+
+        if best[0].pv.is_empty() || best[0].mate_in().is_some() {
+            break;
+        }
+    }
+    best
+}
+
+// A search running on its own thread, so a window can keep drawing or a
+// protocol loop keep reading commands meanwhile. Dropping it stops the search.
+pub struct SearchThread {
+    stop: Arc<AtomicBool>,
+    receiver: Receiver<SearchInfo>,
+}
+
+impl SearchThread {
+    pub fn spawn(
+        position: Position,
+        history: Vec<Position>,
+        limits: SearchLimits,
+        table: Arc<Mutex<TranspositionTable>>,
+        tablebases: Option<Arc<Tablebases>>,
+        on_info: impl FnMut(&SearchInfo) + Send + 'static,
+    ) -> SearchThread {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let thread_stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            // In the tablebases there is nothing to search for
+            if let Some((mv, _)) = tablebases.and_then(|tablebases| tablebases.best_move(&position)) {
+                let _ = sender.send(SearchInfo { pv: vec![mv], ..SearchInfo::default() });
+                return;
+            }
+            let mut table = table.lock().unwrap();
+            let result = search(&position, &history, limits, &mut table, &thread_stop, on_info);
+            // Nobody is listening any more if the search was dropped
+            let _ = sender.send(result);
+        });
+
+        SearchThread { stop, receiver }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    // The result, if the search has finished
+    pub fn try_result(&self) -> Option<SearchInfo> {
+        self.receiver.try_recv().ok()
+    }
+
+    // Block until the search finishes
+    pub fn wait(&self) -> Option<SearchInfo> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for SearchThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// A search that goes on until stopped, for showing what the engine makes of a
+// position. Dropping it stops the search.
+pub struct AnalysisThread {
+    stop: Arc<AtomicBool>,
+    receiver: Receiver<Vec<SearchInfo>>,
+}
+
+impl AnalysisThread {
+    pub fn spawn(position: Position, history: Vec<Position>, count: usize, table: Arc<Mutex<TranspositionTable>>) -> AnalysisThread {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let thread_stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            let mut table = table.lock().unwrap();
+            search_lines(&position, &history, SearchLimits::default(), count, &mut table, &thread_stop, |lines| {
+                let _ = sender.send(lines.to_vec());
+            });
+        });
+
+        AnalysisThread { stop, receiver }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    // The lines of the deepest iteration finished since the last call, if any was
+    pub fn try_lines(&self) -> Option<Vec<SearchInfo>> {
+        self.receiver.try_iter().last()
+    }
+}
+
+impl Drop for AnalysisThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::square::Square;
+
+    fn best_move(fen: &str, depth: u32) -> SearchInfo {
+        let position = Position::from_fen(fen).unwrap();
+        let limits = SearchLimits { depth: Some(depth), movetime: None };
+        search(&position, &[], limits, &mut TranspositionTable::new(1), &AtomicBool::new(false), |_| {})
+    }
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let info = best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(info.best_move(), Some(Move::new(Square::new(0, 7), Square::new(0, 0))));
+        assert_eq!(info.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn test_takes_hanging_queen() {
+        let info = best_move("4k3/8/8/3q4/8/2N5/8/4K3 w - - 0 1", 2);
+        assert_eq!(info.best_move(), Some(Move::new(Square::new(2, 5), Square::new(3, 3))));
+    }
+
+    #[test]
+    fn test_stops_on_request() {
+        let position = Position::default();
+        let mut table = TranspositionTable::new(1);
+        let info = search(&position, &[], SearchLimits::default(), &mut table, &AtomicBool::new(true), |_| {});
+        // Even a search stopped straight away has a move to play
+        assert!(info.best_move().is_some());
+    }
+
+    #[test]
+    fn test_several_lines() {
+        let position = Position::from_fen("4k3/8/8/3q4/8/2N5/8/4K3 w - - 0 1").unwrap();
+        let limits = SearchLimits { depth: Some(2), movetime: None };
+        let lines = search_lines(&position, &[], limits, 3, &mut TranspositionTable::new(1), &AtomicBool::new(false), |_| {});
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].best_move(), Some(Move::new(Square::new(2, 5), Square::new(3, 3))));
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score && pair[0].best_move() != pair[1].best_move()));
+
+        // Never more lines than there are moves
+        let position = Position::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let lines = search_lines(&position, &[], limits, 5, &mut TranspositionTable::new(1), &AtomicBool::new(false), |_| {});
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn test_analysis_thread() {
+        let table = Arc::new(Mutex::new(TranspositionTable::new(1)));
+        let analysis = AnalysisThread::spawn(Position::default(), Vec::new(), 2, table);
+
+        let start = Instant::now();
+        let lines = loop {
+            if let Some(lines) = analysis.try_lines() {
+                break lines;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_search_thread() {
+        let limits = SearchLimits { depth: None, movetime: Some(Duration::from_millis(100)) };
+        let table = Arc::new(Mutex::new(TranspositionTable::new(1)));
+        let search = SearchThread::spawn(Position::default(), Vec::new(), limits, table, None, |_| {});
+        assert!(search.wait().unwrap().best_move().is_some());
+    }
+}
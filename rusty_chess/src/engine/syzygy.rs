@@ -0,0 +1,1395 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+use memmap2::Mmap;
+
+use crate::board::bitboard::{squares, Bitboard, KING_ATTACKS};
+use crate::board::movegen::Move;
+use crate::board::piece::{Owner, PieceType};
+use crate::board::position::{CastlingRights, Position};
+
+// Probing of Syzygy endgame tablebases. A table holds every position with one
+// set of material: the .rtbw file whether it is won, drawn or lost (WDL) and the
+// .rtbz file how many plies it takes to the next capture or pawn move that keeps
+// that result (DTZ, distance to zeroing). Playing the move with the lowest DTZ
+// wins every won position before the 50-move rule can save the other side.
+//
+// The files are compressed with their own scheme, and reading them follows the
+// reference prober (Ronald de Man's, as found in Stockfish) step by step.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// Flags of each compressed table
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+// Flags of the file as a whole
+const SPLIT: u8 = 1;
+const HAS_PAWNS: u8 = 2;
+
+// Pieces as the files number them, black's with 8 added
+const W_PAWN: u8 = 1;
+const B_PAWN: u8 = 9;
+const MAX_PIECES: usize = 7;
+
+// Result for the side to move, with the 50-move rule taken into account
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    // Lost, but the 50-move rule comes first
+    BlessedLoss,
+    Draw,
+    // Won, but not before the 50-move rule
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_score(score: i32) -> Wdl {
+        match score {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            1 => Wdl::CursedWin,
+            2 => Wdl::Win,
+            _ => Wdl::Draw,
+        }
+    }
+}
+
+// What the tablebases say about a position
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Probe {
+    pub wdl: Wdl,
+    // Plies to the next capture or pawn move, negative when losing and 0 for draws.
+    // Wins and losses the 50-move rule spoils have 100 added.
+    pub dtz: i32,
+}
+
+impl Probe {
+    // Full moves until the winning side can capture or push a pawn
+    pub fn moves(&self) -> i32 {
+        (self.dtz.abs() % 100 + 1) / 2
+    }
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.wdl {
+            Wdl::Win => write!(f, "Tablebase win in {}", self.moves()),
+            Wdl::Loss => write!(f, "Tablebase loss in {}", self.moves()),
+            Wdl::CursedWin | Wdl::BlessedLoss => write!(f, "Tablebase draw by the 50-move rule"),
+            Wdl::Draw => write!(f, "Tablebase draw"),
+        }
+    }
+}
+
+// Index tables the files' position encoding is built from, a1 = 0 to h8 = 63
+struct Encoding {
+    // a2-h7 to 0..47, highest for the pawns nearest the edge and the first rank
+    map_pawns: [usize; 64],
+    // The 28 squares below the a1-h8 diagonal
+    map_b1h1h7: [usize; 64],
+    // The a1-d1-d4 triangle, diagonal squares last
+    map_a1d1d4: [usize; 64],
+    // The 462 ways to place two kings with the first in the triangle
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; 6],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+// Rank minus file, 0 on the a1-h8 diagonal and negative below it
+fn off_diagonal(square: usize) -> i32 {
+    (square >> 3) as i32 - (square & 7) as i32
+}
+
+fn king_attacks(square: usize) -> Bitboard {
+    // The board counts from a8, the files from a1
+    squares(KING_ATTACKS[square ^ 56]).fold(0, |all, attacked| all | 1 << (attacked.index() ^ 56))
+}
+
+fn encoding() -> &'static Encoding {
+    static ENCODING: OnceLock<Encoding> = OnceLock::new();
+    ENCODING.get_or_init(|| {
+        let mut encoding = Encoding {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 6],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                encoding.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        let mut code = 0;
+        let mut diagonal = Vec::new();
+        for square in 0..=27 {
+            if off_diagonal(square) < 0 && square & 7 <= 3 {
+                encoding.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 && square & 7 <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            encoding.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for idx in 0..10 {
+            // b1 is the one square mapped to 0
+            for first in (0..=27).filter(|&first| encoding.map_a1d1d4[first] == idx && (idx != 0 || first == 1)) {
+                for second in 0..64 {
+                    if (king_attacks(first) | 1 << first) & 1 << second != 0 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) > 0 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        encoding.map_kk[idx][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            encoding.map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        encoding.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                let with = if k > 0 { encoding.binomial[k - 1][n - 1] } else { 0 };
+                let without = if k < n { encoding.binomial[k][n - 1] } else { 0 };
+                encoding.binomial[k][n] = with + without;
+            }
+        }
+
+        let mut available: i32 = 47;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..=6 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        encoding.map_pawns[square] = available as usize;
+                        encoding.map_pawns[square ^ 7] = (available - 1) as usize;
+                        available -= 2;
+                    }
+                    encoding.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += encoding.binomial[lead_pawns - 1][encoding.map_pawns[square]];
+                }
+                encoding.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        encoding
+    })
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+// The compressed stream is read as big-endian, with nothing past the end of the file
+fn read_stream(bytes: &[u8], at: usize) -> u32 {
+    (0..4).fold(0, |value, i| value << 8 | *bytes.get(at + i).unwrap_or(&0) as u32)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Wdl,
+    Dtz,
+}
+
+// One compressed table of a file: for a side to move and, with pawns, for the
+// file the leading pawn is on. The offsets point into the file's bytes.
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    min_sym_len: u8,
+    block_size: usize,
+    span: usize,
+    num_blocks: usize,
+    block_length_size: usize,
+    sparse_index_size: usize,
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    // base64[l - min_sym_len] is the lowest Huffman code of length l, padded to 64 bits
+    base64: Vec<u64>,
+    // How many values, less one, each symbol stands for
+    symlen: Vec<u8>,
+    // The order the pieces are encoded in, which also makes up the groups
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    group_len: [usize; MAX_PIECES + 1],
+    // Where the DTZ values for wins, losses, cursed wins and blessed losses are mapped
+    map_idx: [usize; 4],
+}
+
+// Symbols are stored as pairs of 12-bit numbers
+fn btree_left(bytes: &[u8], d: &PairsData, symbol: usize) -> usize {
+    let at = d.btree + 3 * symbol;
+    ((bytes[at + 1] as usize & 0xF) << 8) | bytes[at] as usize
+}
+
+fn btree_right(bytes: &[u8], d: &PairsData, symbol: usize) -> usize {
+    let at = d.btree + 3 * symbol;
+    ((bytes[at + 2] as usize) << 4) | (bytes[at + 1] as usize >> 4)
+}
+
+// A file's bytes. Files are mapped rather than read, so loading one takes no
+// time whatever its size and only the blocks probed are ever read from disk.
+// Owned bytes are for tables put together in memory.
+enum Bytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Default for Bytes {
+    fn default() -> Bytes {
+        Bytes::Owned(Vec::new())
+    }
+}
+
+impl Bytes {
+    fn map(path: &Path) -> io::Result<Bytes> {
+        let file = File::open(path)?;
+        // The tables are only ever read, and nothing is expected to change a
+        // table file while it is in use
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Bytes::Mapped(mmap))
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Mapped(mmap) => mmap,
+            Bytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+struct Table {
+    kind: Kind,
+    bytes: Bytes,
+    // The material with the file's first side as white, and as black
+    key: String,
+    key2: String,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // Pawns of the leading side and of the other one
+    pawn_count: [usize; 2],
+    items: Vec<PairsData>,
+    // Start of the DTZ value maps
+    map: usize,
+}
+
+impl Table {
+    // `name` is the file name without extension, like "KRPvKR"
+    fn load(kind: Kind, name: &str, bytes: Bytes) -> Option<Table> {
+        let magic = if kind == Kind::Wdl { WDL_MAGIC } else { DTZ_MAGIC };
+        if bytes.len() < 5 || bytes[0..4] != magic {
+            return None;
+        }
+        let mut table = Table::new(kind, name, bytes)?;
+        if table.has_pawns != (table.bytes[4] & HAS_PAWNS != 0) {
+            return None;
+        }
+        table.set()?;
+        Some(table)
+    }
+
+    // The table for the material named, its parts still to be found
+    fn new(kind: Kind, name: &str, bytes: Bytes) -> Option<Table> {
+        let (white, black) = name.split_once('v')?;
+        let count = |side: &str, piece: char| side.chars().filter(|&c| c == piece).count();
+        let pawns = [count(white, 'P'), count(black, 'P')];
+        let has_unique_pieces = [white, black].iter().any(|side| "QRBN".chars().any(|piece| count(side, piece) == 1));
+        // With pawns on both sides the side with fewer leads, it compresses better
+        let white_leads = pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]);
+
+        let table = Table {
+            kind,
+            bytes,
+            key: name.to_string(),
+            key2: format!("{}v{}", black, white),
+            piece_count: white.len() + black.len(),
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique_pieces,
+            pawn_count: if white_leads { pawns } else { [pawns[1], pawns[0]] },
+            items: vec![PairsData::default(); 8],
+            map: 0,
+        };
+        if table.piece_count > MAX_PIECES {
+            return None;
+        }
+        Some(table)
+    }
+
+    fn sides(&self) -> usize {
+        if self.kind == Kind::Wdl && self.key != self.key2 {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn max_file(&self) -> usize {
+        if self.has_pawns {
+            3
+        } else {
+            0
+        }
+    }
+
+    fn item(&self, side: usize, file: usize) -> usize {
+        let side = if self.kind == Kind::Wdl { side % 2 } else { 0 };
+        side * 4 + if self.has_pawns { file } else { 0 }
+    }
+
+    // Find the parts of the file: the piece order of each table, then the
+    // Huffman codes, the DTZ maps, the indices into the blocks and the blocks
+    fn set(&mut self) -> Option<()> {
+        let bytes = std::mem::take(&mut self.bytes);
+        let result = self.set_from(&bytes);
+        self.bytes = bytes;
+        result
+    }
+
+    fn set_from(&mut self, bytes: &[u8]) -> Option<()> {
+        if (self.key != self.key2) != (bytes[4] & SPLIT != 0) {
+            return None;
+        }
+        let mut at = 5;
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let sides = self.sides();
+
+        for file in 0..=self.max_file() {
+            let order_byte = *bytes.get(at)?;
+            let pawn_order = if both_pawns { *bytes.get(at + 1)? } else { 0xFF };
+            let order = [[order_byte & 0xF, pawn_order & 0xF], [order_byte >> 4, pawn_order >> 4]];
+            at += 1 + both_pawns as usize;
+
+            for k in 0..self.piece_count {
+                let byte = *bytes.get(at)?;
+                for side in 0..sides {
+                    let item = self.item(side, file);
+                    self.items[item].pieces[k] = if side == 0 { byte & 0xF } else { byte >> 4 };
+                }
+                at += 1;
+            }
+            for (side, order) in order.iter().enumerate().take(sides) {
+                let item = self.item(side, file);
+                self.set_groups(item, order, file);
+            }
+        }
+        at += at & 1;
+
+        for file in 0..=self.max_file() {
+            for side in 0..sides {
+                let item = self.item(side, file);
+                at = set_sizes(&mut self.items[item], bytes, at)?;
+            }
+        }
+
+        if self.kind == Kind::Dtz {
+            at = self.set_dtz_map(bytes, at)?;
+        }
+
+        for file in 0..=self.max_file() {
+            for side in 0..sides {
+                let item = self.item(side, file);
+                let d = &mut self.items[item];
+                d.sparse_index = at;
+                at += d.sparse_index_size * 6;
+            }
+        }
+        for file in 0..=self.max_file() {
+            for side in 0..sides {
+                let item = self.item(side, file);
+                let d = &mut self.items[item];
+                d.block_length = at;
+                at += d.block_length_size * 2;
+            }
+        }
+        for file in 0..=self.max_file() {
+            for side in 0..sides {
+                let item = self.item(side, file);
+                let d = &mut self.items[item];
+                at = (at + 0x3F) & !0x3F;
+                d.data = at;
+                at += d.num_blocks * d.block_size;
+            }
+        }
+
+        // Tables that hold a single value have no data, so the alignment can run past the end
+        let used = self.items.iter().filter(|d| d.flags & SINGLE_VALUE == 0).map(|d| d.data + d.num_blocks * d.block_size);
+        if used.max().unwrap_or(0) > bytes.len() {
+            return None;
+        }
+        Some(())
+    }
+
+    // Split the pieces into the groups they are encoded in: the leading pawns or
+    // pieces, then the other side's pawns, then runs of the same piece
+    fn set_groups(&mut self, item: usize, order: &[u8; 2], file: usize) {
+        let encoding = encoding();
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let d = &mut self.items[item];
+
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        d.group_len[n] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        // The groups are multiplied together in the table's own order, the
+        // first group at order[0] and the other side's pawns at order[1]
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                d.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    encoding.lead_pawns_size[d.group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                d.group_idx[1] = idx;
+                idx *= encoding.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= encoding.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    fn set_dtz_map(&mut self, bytes: &[u8], mut at: usize) -> Option<usize> {
+        self.map = at;
+        for file in 0..=self.max_file() {
+            let item = self.item(0, file);
+            let flags = self.items[item].flags;
+            if flags & MAPPED == 0 {
+                continue;
+            }
+            if flags & WIDE != 0 {
+                at += at & 1;
+                for i in 0..4 {
+                    self.items[item].map_idx[i] = (at - self.map) / 2 + 1;
+                    at += 2 * read_u16(bytes, at) as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    self.items[item].map_idx[i] = at - self.map + 1;
+                    at += *bytes.get(at)? as usize + 1;
+                }
+            }
+        }
+        Some(at + (at & 1))
+    }
+
+    // The value stored for index `idx` of a table
+    fn decompress_pairs(&self, d: &PairsData, idx: u64) -> usize {
+        if d.flags & SINGLE_VALUE != 0 {
+            return d.min_sym_len as usize;
+        }
+        let bytes = &self.bytes;
+
+        // Every `span` values there is an entry saying which block holds the
+        // value in the middle of them, and where in that block it is
+        let k = (idx / d.span as u64) as usize;
+        let entry = d.sparse_index + 6 * k;
+        let mut block = read_u32(bytes, entry) as usize;
+        let mut offset = read_u16(bytes, entry + 4) as i64;
+        offset += (idx % d.span as u64) as i64 - (d.span / 2) as i64;
+
+        let block_length = |block: usize| read_u16(bytes, d.block_length + 2 * block) as i64;
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        // Walk the block's Huffman codes until the symbol that covers the offset
+        let mut at = d.data + block * d.block_size;
+        let mut buffer = (read_stream(bytes, at) as u64) << 32 | read_stream(bytes, at + 4) as u64;
+        at += 8;
+        let mut buffer_size = 64;
+        let min_sym_len = d.min_sym_len as usize;
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while buffer < d.base64[len] {
+                len += 1;
+            }
+            symbol = ((buffer - d.base64[len]) >> (64 - len - min_sym_len)) as usize;
+            symbol += read_u16(bytes, d.lowest_sym + 2 * len) as usize;
+            symbol &= 0xFFFF;
+
+            if offset < d.symlen[symbol] as i64 + 1 {
+                break;
+            }
+            offset -= d.symlen[symbol] as i64 + 1;
+            len += min_sym_len;
+            buffer <<= len;
+            buffer_size -= len;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_stream(bytes, at) as u64) << (64 - buffer_size);
+                at += 4;
+            }
+        }
+
+        // Each symbol stands for a pair of symbols, down to single values
+        while d.symlen[symbol] != 0 {
+            let left = btree_left(bytes, d, symbol);
+            if offset < d.symlen[left] as i64 + 1 {
+                symbol = left;
+            } else {
+                offset -= d.symlen[left] as i64 + 1;
+                symbol = btree_right(bytes, d, symbol);
+            }
+        }
+        btree_left(bytes, d, symbol)
+    }
+
+    // Turn a stored value into a WDL score from -2 to 2, or a DTZ in plies
+    fn map_score(&self, file: usize, value: usize, wdl: i32) -> i32 {
+        if self.kind == Kind::Wdl {
+            return value as i32 - 2;
+        }
+
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let d = &self.items[self.item(0, file)];
+        let mut value = value;
+        if d.flags & MAPPED != 0 {
+            let idx = d.map_idx[WDL_MAP[(wdl + 2) as usize]] + value;
+            value = if d.flags & WIDE != 0 {
+                read_u16(&self.bytes, self.map + 2 * idx) as usize
+            } else {
+                self.bytes[self.map + idx] as usize
+            };
+        }
+
+        let mut value = value as i32;
+        if (wdl == 2 && d.flags & WIN_PLIES == 0) || (wdl == -2 && d.flags & LOSS_PLIES == 0) || wdl == 1 || wdl == -1 {
+            value *= 2;
+        }
+        value + 1
+    }
+
+    // The stored value for a position with this table's material. `None` for
+    // a DTZ table that only holds the other side to move.
+    fn probe(&self, position: &Position, wdl: i32) -> Option<i32> {
+        let (item, file, idx) = self.encode(position)?;
+        Some(self.map_score(file, self.decompress_pairs(&self.items[item], idx), wdl))
+    }
+
+    // Which of the file's tables holds the position, for which file of the
+    // leading pawn, and where in that table it is
+    fn encode(&self, position: &Position) -> Option<(usize, usize, u64)> {
+        let encoding = encoding();
+        let mut squares_list = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns_count = 0;
+        let mut lead_pawns: Bitboard = 0;
+        let mut file = 0;
+
+        // The files only hold white as the stronger side, and only white to
+        // move when both sides have the same pieces, so colours may need swapping
+        let black_to_move = position.turn == Owner::Black;
+        let symmetric_black_to_move = self.key == self.key2 && black_to_move;
+        let black_stronger = material_key(position) != self.key;
+        let flip = symmetric_black_to_move || black_stronger;
+        let flip_colour = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip ^ black_to_move) as usize;
+
+        if self.has_pawns {
+            // The leading pawn is the one furthest toward the edge and then the back rank
+            let pawn = self.items[self.item(0, 0)].pieces[0] ^ flip_colour;
+            let owner = if pawn == W_PAWN { Owner::White } else { Owner::Black };
+            lead_pawns = position.bitboard(owner, PieceType::Pawn);
+            for square in squares(lead_pawns) {
+                squares_list[size] = tb_square(square.index()) ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns_count = size;
+
+            let mut lead = 0;
+            for i in 1..lead_pawns_count {
+                if encoding.map_pawns[squares_list[i]] > encoding.map_pawns[squares_list[lead]] {
+                    lead = i;
+                }
+            }
+            squares_list.swap(0, lead);
+            file = (squares_list[0] & 7).min(7 - (squares_list[0] & 7));
+        }
+
+        if self.kind == Kind::Dtz {
+            let flags = self.items[self.item(stm, file)].flags;
+            // Symmetric tables without pawns serve both sides
+            let both_sides = self.key == self.key2 && !self.has_pawns;
+            if (flags & STM) as usize != stm && !both_sides {
+                return None;
+            }
+        }
+
+        for square in squares(position.occupied() ^ lead_pawns) {
+            let piece = position.piece_at(square).unwrap();
+            squares_list[size] = tb_square(square.index()) ^ flip_squares;
+            pieces[size] = tb_piece(piece.owner, piece.piece_type) ^ flip_colour;
+            size += 1;
+        }
+
+        let item = self.item(stm, file);
+        let d = &self.items[item];
+
+        // Put the pieces in the order the table encodes them in
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| d.pieces[i] == pieces[j]) {
+                pieces.swap(i, j);
+                squares_list.swap(i, j);
+            }
+        }
+        let squares = &mut squares_list[..size];
+
+        // Mirror so the leading piece is on the a to d files
+        if squares[0] & 7 > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = encoding.lead_pawn_idx[lead_pawns_count][squares[0]];
+            squares[1..lead_pawns_count].sort_by_key(|&square| encoding.map_pawns[square]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                idx += encoding.binomial[i][encoding.map_pawns[square]];
+            }
+        } else {
+            // Without pawns the board can be mirrored further, so the leading
+            // piece is on the first four ranks and below the a1-h8 diagonal
+            if squares[0] >> 3 > 3 {
+                for square in squares.iter_mut() {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                if off_diagonal(squares[i]) == 0 {
+                    continue;
+                }
+                if off_diagonal(squares[i]) > 0 {
+                    for square in squares[i..].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let rank = |square: usize| (square >> 3) as u64;
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+
+                idx = if off_diagonal(squares[0]) != 0 {
+                    (encoding.map_a1d1d4[squares[0]] as u64 * 63 + (squares[1] as u64 - adjust1)) * 62 + squares[2] as u64 - adjust2
+                } else if off_diagonal(squares[1]) != 0 {
+                    (6 * 63 + rank(squares[0]) * 28 + encoding.map_b1h1h7[squares[1]] as u64) * 62 + squares[2] as u64 - adjust2
+                } else if off_diagonal(squares[2]) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + rank(squares[0]) * 7 * 28 + (rank(squares[1]) - adjust1) * 28 + encoding.map_b1h1h7[squares[2]] as u64
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(squares[0]) * 7 * 6 + (rank(squares[1]) - adjust1) * 6 + (rank(squares[2]) - adjust2)
+                };
+            } else {
+                idx = encoding.map_kk[encoding.map_a1d1d4[squares[0]]][squares[1]] as u64;
+            }
+        }
+
+        // The remaining groups, each counted among the squares the earlier ones left free
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[start..start + len].sort();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&earlier| square > earlier).count();
+                n += encoding.binomial[i + 1][square - adjust - 8 * remaining_pawns as usize];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+        Some((item, file, idx))
+    }
+}
+
+fn set_sizes(d: &mut PairsData, bytes: &[u8], mut at: usize) -> Option<usize> {
+    d.flags = *bytes.get(at)?;
+    at += 1;
+    if d.flags & SINGLE_VALUE != 0 {
+        d.min_sym_len = *bytes.get(at)?;
+        return Some(at + 1);
+    }
+    if at + 10 > bytes.len() {
+        return None;
+    }
+
+    let size = d.group_idx[d.group_len.iter().position(|&len| len == 0)?];
+    d.block_size = 1 << bytes[at];
+    d.span = 1 << bytes[at + 1];
+    d.sparse_index_size = size.div_ceil(d.span as u64) as usize;
+    let padding = bytes[at + 2] as usize;
+    d.num_blocks = read_u32(bytes, at + 3) as usize;
+    d.block_length_size = d.num_blocks + padding;
+    let max_sym_len = bytes[at + 7];
+    d.min_sym_len = bytes[at + 8];
+    at += 9;
+    if max_sym_len < d.min_sym_len || max_sym_len > 32 {
+        return None;
+    }
+    d.lowest_sym = at;
+
+    // Canonical Huffman codes: longer codes have lower values, so the
+    // lowest code of each length says where the codes of that length start
+    let lengths = (max_sym_len - d.min_sym_len + 1) as usize;
+    if at + 2 * lengths + 2 > bytes.len() {
+        return None;
+    }
+    d.base64 = vec![0; lengths];
+    for i in (0..lengths - 1).rev() {
+        let lowest = read_u16(bytes, d.lowest_sym + 2 * i) as u64;
+        let lowest_next = read_u16(bytes, d.lowest_sym + 2 * (i + 1)) as u64;
+        d.base64[i] = d.base64[i + 1].wrapping_add(lowest).wrapping_sub(lowest_next) / 2;
+    }
+    for (i, base) in d.base64.iter_mut().enumerate() {
+        *base <<= 64 - i - d.min_sym_len as usize;
+    }
+    at += 2 * lengths;
+
+    let symbols = read_u16(bytes, at) as usize;
+    at += 2;
+    d.btree = at;
+    if at + 3 * symbols > bytes.len() {
+        return None;
+    }
+
+    // The compression replaces the commonest pair of symbols with a new one
+    // over and over, so every symbol is a tree of the values it expands to
+    d.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for symbol in 0..symbols {
+        if !visited[symbol] {
+            d.symlen[symbol] = set_symlen(d, bytes, symbol, &mut visited)?;
+        }
+    }
+    Some(at + 3 * symbols + (symbols & 1))
+}
+
+fn set_symlen(d: &mut PairsData, bytes: &[u8], symbol: usize, visited: &mut [bool]) -> Option<u8> {
+    visited[symbol] = true;
+    let right = btree_right(bytes, d, symbol);
+    if right == 0xFFF {
+        return Some(0);
+    }
+    let left = btree_left(bytes, d, symbol);
+    for child in [left, right] {
+        if !*visited.get(child)? {
+            d.symlen[child] = set_symlen(d, bytes, child, visited)?;
+        }
+    }
+    Some(d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1))
+}
+
+// Square numbers as the files have them, a1 = 0
+fn tb_square(index: usize) -> usize {
+    index ^ 56
+}
+
+fn tb_piece(owner: Owner, piece_type: PieceType) -> u8 {
+    let code = match piece_type {
+        PieceType::Pawn => W_PAWN,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook => 4,
+        PieceType::Queen => 5,
+        PieceType::King => 6,
+    };
+    if owner == Owner::White {
+        code
+    } else {
+        code + B_PAWN - W_PAWN
+    }
+}
+
+// The material as a table name with white first, like "KRPvKR"
+fn material_key(position: &Position) -> String {
+    let side = |owner: Owner| -> String {
+        [PieceType::King, PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight, PieceType::Pawn]
+            .iter()
+            .flat_map(|&piece_type| std::iter::repeat_n(piece_type.to_char(), position.bitboard(owner, piece_type).count_ones() as usize))
+            .collect()
+    };
+    format!("{}v{}", side(Owner::White), side(Owner::Black))
+}
+
+fn sign(value: i32) -> i32 {
+    value.signum()
+}
+
+// The DTZ of a position whose best move captures or pushes a pawn
+fn dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+fn is_zeroing(position: &Position, mv: Move) -> bool {
+    position.is_capture(mv) || position.piece_at(mv.from).is_some_and(|piece| piece.piece_type == PieceType::Pawn)
+}
+
+// A table's name and whether it is the DTZ one. A table that failed to load
+// is remembered as `None`.
+type TableId = (String, bool);
+
+// Syzygy tables found in a directory, loaded the first time they are needed
+pub struct Tablebases {
+    directory: PathBuf,
+    // Table names, like "KQvK"
+    names: Vec<String>,
+    max_pieces: usize,
+    loaded: Mutex<HashMap<TableId, Option<Arc<Table>>>>,
+}
+
+impl Tablebases {
+    // Fails when the directory can't be read or holds no WDL tables
+    pub fn open(directory: &Path) -> io::Result<Tablebases> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "rtbw") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+        if names.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no Syzygy tables in {}", directory.display())));
+        }
+
+        let max_pieces = names.iter().map(|name| name.len() - 1).max().unwrap_or(0);
+        Ok(Tablebases { directory: directory.to_path_buf(), names, max_pieces, loaded: Mutex::new(HashMap::new()) })
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    // The most pieces, kings included, any of the tables has
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    // Whether the tables could have the position. They leave out castling.
+    pub fn covers(&self, position: &Position) -> bool {
+        position.occupied().count_ones() as usize <= self.max_pieces && position.castling == CastlingRights::none()
+    }
+
+    fn table(&self, position: &Position, kind: Kind) -> Option<Arc<Table>> {
+        let key = material_key(position);
+        let (white, black) = key.split_once('v')?;
+        let swapped = format!("{}v{}", black, white);
+        let name = [key.clone(), swapped].into_iter().find(|name| self.names.contains(name))?;
+
+        let dtz = kind == Kind::Dtz;
+        let mut loaded = self.loaded.lock().unwrap();
+        loaded
+            .entry((name.clone(), dtz))
+            .or_insert_with(|| {
+                let extension = if dtz { "rtbz" } else { "rtbw" };
+                let bytes = Bytes::map(&self.directory.join(format!("{}.{}", name, extension))).ok()?;
+                Table::load(kind, &name, bytes).map(Arc::new)
+            })
+            .clone()
+    }
+
+    // WDL score of the position as stored, from -2 to 2
+    fn probe_wdl_table(&self, position: &Position) -> Option<i32> {
+        if position.occupied().count_ones() == 2 {
+            return Some(0);
+        }
+        self.table(position, Kind::Wdl)?.probe(position, 0)
+    }
+
+    // The tables don't know about en passant, and for some positions what is
+    // stored is only right if no capture does better, so captures (and with
+    // `zeroing` pawn moves) are searched first. The flag says whether the best
+    // move is one of those, in which case the DTZ table can't be trusted.
+    fn search(&self, position: &Position, zeroing: bool) -> Option<(i32, bool)> {
+        let moves = position.legal_moves();
+        let mut best = -2;
+        let mut searched = 0;
+
+        for &mv in &moves {
+            let searchable = position.is_capture(mv) || (zeroing && is_zeroing(position, mv));
+            if !searchable {
+                continue;
+            }
+            searched += 1;
+
+            let mut child = *position;
+            child.apply_move(mv);
+            let (value, _) = self.search(&child, false)?;
+            let value = -value;
+            if value > best {
+                best = value;
+                if value >= 2 {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = if no_more_moves { best } else { self.probe_wdl_table(position)? };
+
+        if best >= value {
+            return Some((best, best > 0 || no_more_moves));
+        }
+        Some((value, false))
+    }
+
+    fn dtz(&self, position: &Position) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(position, true)?;
+        if wdl == 0 {
+            return Some(0);
+        }
+        if zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        let table = self.table(position, Kind::Dtz)?;
+        if let Some(dtz) = table.probe(position, wdl) {
+            let cursed = wdl == 1 || wdl == -1;
+            return Some((dtz + if cursed { 100 } else { 0 }) * sign(wdl));
+        }
+
+        // The table only has the other side to move, so look one move ahead
+        let mut min_dtz = i32::MAX;
+        for mv in position.legal_moves() {
+            let zeroing = is_zeroing(position, mv);
+            let mut child = *position;
+            child.apply_move(mv);
+
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search(&child, false)?.0)
+            } else {
+                -self.dtz(&child)?
+            };
+            if dtz == 1 && child.is_checkmate() {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += sign(dtz);
+            }
+            if dtz < min_dtz && sign(dtz) == sign(wdl) {
+                min_dtz = dtz;
+            }
+        }
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    // `None` when the position isn't in the tables
+    pub fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.search(position, false).map(|(wdl, _)| Wdl::from_score(wdl))
+    }
+
+    pub fn probe(&self, position: &Position) -> Option<Probe> {
+        if !self.covers(position) {
+            return None;
+        }
+        let (wdl, _) = self.search(position, false)?;
+        let dtz = self.dtz(position)?;
+        Some(Probe { wdl: Wdl::from_score(wdl), dtz })
+    }
+
+    // The move that wins fastest by DTZ, or failing a win draws, or failing that
+    // holds out longest. With the probe of the position itself.
+    pub fn best_move(&self, position: &Position) -> Option<(Move, Probe)> {
+        let probe = self.probe(position)?;
+
+        let mut best: Option<((i32, i32), Move)> = None;
+        for mv in position.legal_moves() {
+            let mut child = *position;
+            child.apply_move(mv);
+
+            let dtz = if child.is_checkmate() {
+                1
+            } else if child.halfmove_clock == 0 {
+                -dtz_before_zeroing(self.search(&child, false)?.0)
+            } else {
+                let dtz = -self.dtz(&child)?;
+                dtz + sign(dtz)
+            };
+
+            // Wins in time first, then wins the 50-move rule spoils, then
+            // draws and losses, the longest first
+            let rank = if dtz > 0 && dtz + position.halfmove_clock as i32 <= 100 {
+                (0, dtz)
+            } else if dtz > 0 {
+                (1, dtz)
+            } else if dtz == 0 {
+                (2, 0)
+            } else {
+                (3, dtz)
+            };
+            if best.is_none_or(|(best_rank, _)| rank < best_rank) {
+                best = Some((rank, mv));
+            }
+        }
+        best.map(|(_, mv)| (mv, probe))
+    }
+}
+
+// A probe made on its own thread, so a window showing the result keeps
+// drawing while the tables are read
+pub struct ProbeThread {
+    receiver: Receiver<Option<Probe>>,
+}
+
+impl ProbeThread {
+    pub fn spawn(tablebases: Arc<Tablebases>, position: Position) -> ProbeThread {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // Nobody is listening any more if the probe was dropped
+            let _ = sender.send(tablebases.probe(&position));
+        });
+        ProbeThread { receiver }
+    }
+
+    // What the tables say, once the probe has finished
+    pub fn try_result(&self) -> Option<Option<Probe>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod writer;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    // A file whose one or two tables each hold a single value, enough to follow
+    // a probe through everything but the decompression
+    fn single_value_file(magic: [u8; 4], split: bool, pieces: &[u8], values: &[u8]) -> Vec<u8> {
+        let mut bytes = magic.to_vec();
+        bytes.push(split as u8);
+        bytes.push(0);
+        bytes.extend(pieces.iter().map(|&piece| piece | piece << 4));
+        if bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        for &value in values {
+            bytes.extend([SINGLE_VALUE, value]);
+        }
+        bytes
+    }
+
+    // Tables in a directory of their own, which goes when they do
+    struct TempTables {
+        tablebases: Option<Tablebases>,
+        directory: PathBuf,
+    }
+
+    impl Deref for TempTables {
+        type Target = Tablebases;
+
+        fn deref(&self) -> &Tablebases {
+            self.tablebases.as_ref().unwrap()
+        }
+    }
+
+    impl Drop for TempTables {
+        fn drop(&mut self) {
+            // Unmap the files first, some systems won't delete them otherwise
+            self.tablebases.take();
+            let _ = fs::remove_dir_all(&self.directory);
+        }
+    }
+
+    // KQvK won for white, white to move stored in the DTZ table as 5 moves
+    // Each test writes its own copy, as they run at the same time
+    fn kqvk(test: &str) -> TempTables {
+        let directory = env::temp_dir().join(format!("rusty_chess_syzygy_{}_{}", std::process::id(), test));
+        fs::create_dir_all(&directory).unwrap();
+        let pieces = [5, 6, 14];
+        fs::write(directory.join("KQvK.rtbw"), single_value_file(WDL_MAGIC, true, &pieces, &[4, 0])).unwrap();
+        fs::write(directory.join("KQvK.rtbz"), single_value_file(DTZ_MAGIC, true, &pieces, &[5])).unwrap();
+        let tablebases = Tablebases::open(&directory).unwrap();
+        TempTables { tablebases: Some(tablebases), directory }
+    }
+
+    fn position(fen: &str) -> Position {
+        Position::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn test_encoding_tables() {
+        let encoding = encoding();
+        assert_eq!(encoding.map_kk.iter().flatten().max(), Some(&461));
+        assert_eq!(encoding.map_a1d1d4[1], 0);
+        assert_eq!(encoding.map_a1d1d4[27], 9);
+        assert_eq!(encoding.map_b1h1h7.iter().max(), Some(&27));
+        assert_eq!(encoding.binomial[2][5], 10);
+        // a2 and h2 are the edge-most, lowest pawn squares
+        assert_eq!((encoding.map_pawns[8], encoding.map_pawns[15]), (47, 46));
+        assert_eq!(encoding.lead_pawns_size[1][0], 6);
+    }
+
+    #[test]
+    fn test_decompress_pairs() {
+        // Two one-bit symbols that stand for themselves, in two blocks of 8 values
+        let mut bytes = vec![0, 0];
+        bytes.extend([0, 0xF0, 0xFF, 1, 0xF0, 0xFF]);
+        bytes.extend([0, 0, 0, 0, 4, 0, 1, 0, 0, 0, 4, 0]);
+        bytes.extend([7, 0, 7, 0]);
+        bytes.extend([0b1011_0010, 0, 0, 0, 0, 0, 0, 0, 0b0110_1001, 0, 0, 0, 0, 0, 0, 0]);
+        let d = PairsData {
+            min_sym_len: 1,
+            block_size: 8,
+            span: 8,
+            btree: 2,
+            sparse_index: 8,
+            block_length: 20,
+            data: 24,
+            base64: vec![0],
+            symlen: vec![0, 0],
+            ..PairsData::default()
+        };
+        let table = Table {
+            kind: Kind::Wdl,
+            bytes: Bytes::Owned(bytes),
+            key: String::new(),
+            key2: String::new(),
+            piece_count: 0,
+            has_pawns: false,
+            has_unique_pieces: false,
+            pawn_count: [0; 2],
+            items: Vec::new(),
+            map: 0,
+        };
+
+        let values: Vec<usize> = (0..16).map(|idx| table.decompress_pairs(&d, idx)).collect();
+        assert_eq!(values, vec![1, 0, 1, 1, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_material_key() {
+        assert_eq!(material_key(&position("8/8/8/4k3/8/8/3PP3/R3K3 w - - 0 1")), "KRPPvK");
+        assert_eq!(material_key(&position("8/8/8/4k3/8/8/8/4K3 w - - 0 1")), "KvK");
+    }
+
+    #[test]
+    fn test_probe_wdl() {
+        let tablebases = kqvk("wdl");
+        assert_eq!(tablebases.max_pieces(), 3);
+
+        assert_eq!(tablebases.probe_wdl(&position("7Q/8/8/8/4k3/8/8/K7 w - - 0 1")), Some(Wdl::Win));
+        assert_eq!(tablebases.probe_wdl(&position("7Q/8/8/8/4k3/8/8/K7 b - - 0 1")), Some(Wdl::Loss));
+        // Black to move takes the queen, which the capture search finds
+        assert_eq!(tablebases.probe_wdl(&position("8/8/8/8/4k3/4Q3/8/K7 b - - 0 1")), Some(Wdl::Draw));
+        // With black as the stronger side the colours are swapped
+        assert_eq!(tablebases.probe_wdl(&position("7q/8/8/8/4K3/8/8/k7 w - - 0 1")), Some(Wdl::Loss));
+        // No table for these
+        assert_eq!(tablebases.probe_wdl(&position("7R/8/8/8/4k3/8/8/K7 w - - 0 1")), None);
+        assert_eq!(tablebases.probe_wdl(&Position::default()), None);
+    }
+
+    #[test]
+    fn test_probe_dtz() {
+        let tablebases = kqvk("dtz");
+
+        let win = tablebases.probe(&position("7Q/8/8/8/4k3/8/8/K7 w - - 0 1")).unwrap();
+        assert_eq!(win, Probe { wdl: Wdl::Win, dtz: 11 });
+        assert_eq!(win.to_string(), "Tablebase win in 6");
+
+        // Only white to move is stored, so black's DTZ comes from a move ahead
+        let loss = tablebases.probe(&position("7Q/8/8/8/4k3/8/8/K7 b - - 0 1")).unwrap();
+        assert_eq!(loss, Probe { wdl: Wdl::Loss, dtz: -12 });
+        assert_eq!(loss.to_string(), "Tablebase loss in 6");
+    }
+
+    #[test]
+    fn test_best_move_keeps_the_win() {
+        let tablebases = kqvk("best_move");
+        let start = position("8/8/8/8/8/3k4/8/K3Q3 w - - 0 1");
+
+        let (mv, probe) = tablebases.best_move(&start).unwrap();
+        assert_eq!(probe.wdl, Wdl::Win);
+        let mut next = start;
+        next.apply_move(mv);
+        assert_eq!(tablebases.probe_wdl(&next), Some(Wdl::Loss));
+    }
+
+    // Writes the files in tests/syzygy_generated, which are checked in, and
+    // probes every position in them. Run it again after changing the writer
+    // with `cargo test --release generate_test_tables -- --ignored`
+    #[test]
+    #[ignore]
+    fn generate_test_tables() {
+        writer::write_test_tables(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/syzygy_generated")).unwrap();
+    }
+
+    // Tables from the writer above. Written and read by this crate alike, so
+    // they only show the two agree with each other; `test_real_tables` below
+    // is what shows the reader can read the published files.
+    #[test]
+    fn test_generated_tables() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/syzygy_generated");
+        let tablebases = Tablebases::open(&directory).unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&position("8/8/8/8/8/8/8/KN2k3 w - - 0 1")), Some(Wdl::Draw));
+        assert_eq!(tablebases.probe_wdl(&position("8/8/8/8/8/8/4P3/4K2k w - - 0 1")), Some(Wdl::Win));
+        assert_eq!(tablebases.probe_wdl(&position("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")), Some(Wdl::Loss));
+        assert_eq!(tablebases.probe_wdl(&position("4k3/8/8/4P3/8/8/8/4K3 w - - 0 1")), Some(Wdl::Draw));
+
+        // Mates in one, and the one reply before them. The KQvK file holds
+        // white to move and the KRvK one black to move.
+        let probe = |fen: &str| tablebases.probe(&position(fen)).unwrap();
+        assert_eq!(probe("k7/7Q/1K6/8/8/8/8/8 w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 1 });
+        assert_eq!(probe("k7/7Q/1K6/8/8/8/8/8 b - - 0 1"), Probe { wdl: Wdl::Loss, dtz: -2 });
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 1 });
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/7R b - - 0 1"), Probe { wdl: Wdl::Loss, dtz: -2 });
+        // The longest wins there are, mate in 10 with the queen and in 16 with the rook
+        assert_eq!(probe("K7/1Q6/8/8/5k2/8/8/8 w - - 0 1").dtz, 19);
+        assert_eq!(probe("K7/1R6/2k5/8/8/8/8/8 w - - 0 1").dtz, 31);
+        // Pawn moves are zeroing, so the king takes the opposition and then the pawn goes
+        assert_eq!(probe("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").dtz, 1);
+        assert_eq!(probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 3 });
+        assert_eq!(probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Probe { wdl: Wdl::Loss, dtz: -4 });
+        // The longest queen win again, with black as the stronger side
+        assert_eq!(probe("8/8/8/5K2/8/8/1q6/k7 b - - 0 1").dtz, 19);
+    }
+
+    // Needs the published KQvK, KRvK, KPvK and KRvKP files copied into
+    // tests/syzygy, see the README there. Only values any rounding of DTZ
+    // the files may use leaves alone are checked.
+    #[test]
+    #[ignore = "needs the published tables in tests/syzygy"]
+    fn test_real_tables() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/syzygy");
+        let tablebases = Tablebases::open(&directory).unwrap();
+        let probe = |fen: &str| tablebases.probe(&position(fen)).unwrap();
+
+        // Mates in one
+        assert_eq!(probe("k7/7Q/1K6/8/8/8/8/8 w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 1 });
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 1 });
+        assert_eq!(probe("k7/7Q/1K6/8/8/8/8/8 b - - 0 1").wdl, Wdl::Loss);
+        assert_eq!(probe("8/8/8/5K2/8/8/1q6/k7 b - - 0 1").wdl, Wdl::Win);
+
+        // Promoting at once, and taking the opposition before the pawn can go
+        assert_eq!(probe("8/4P3/8/8/8/8/k7/4K3 w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 1 });
+        assert_eq!(probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 3 });
+        assert_eq!(probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").wdl, Wdl::Loss);
+        assert_eq!(probe("4k3/8/8/4P3/8/8/8/4K3 w - - 0 1"), Probe { wdl: Wdl::Draw, dtz: 0 });
+
+        // The rook stands on the pawn's queening square, and the black king is too far away to help
+        assert_eq!(probe("6k1/8/8/8/8/8/p7/R5K1 w - - 0 1"), Probe { wdl: Wdl::Win, dtz: 1 });
+        assert_eq!(probe("6k1/8/8/8/8/8/p7/R5K1 b - - 0 1").wdl, Wdl::Loss);
+
+        // Playing the best moves mates KRvK without the defence finding a way out
+        let mut position = position("8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+        for _ in 0..100 {
+            if position.is_checkmate() {
+                break;
+            }
+            let (mv, _) = tablebases.best_move(&position).unwrap();
+            position.apply_move(mv);
+        }
+        assert!(position.is_checkmate());
+    }
+}
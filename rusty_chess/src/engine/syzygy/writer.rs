@@ -0,0 +1,592 @@
+// Writes the small Syzygy files in tests/syzygy_generated. The endings are
+// solved here by retrograde analysis, and the files put together the way the
+// reference generator lays them out: runs of values paired up over and over,
+// Huffman coded into blocks, with the DTZ values of some tables mapped.
+// Reading them back goes through every part of the reader, but as the same
+// crate writes and reads them they are no stand-in for the published files.
+//
+// Only endings without two pieces of the same kind and without black pawns
+// are handled, which is all the tests need.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::*;
+use crate::board::piece::Piece;
+use crate::board::square::Square;
+
+// The endings written, the side to move whose DTZ values are stored and how.
+// Between them they have byte and wide DTZ maps, values in plies and values
+// rounded to moves, and tables of a single value.
+const ENDINGS: [(&str, Owner, u8); 5] = [
+    ("KNvK", Owner::White, 0),
+    ("KBvK", Owner::White, 0),
+    ("KRvK", Owner::Black, MAPPED | WIDE | LOSS_PLIES),
+    ("KQvK", Owner::White, MAPPED | WIN_PLIES),
+    ("KPvK", Owner::White, 0),
+];
+
+const BLOCK_SIZE_LOG: u8 = 6;
+const SPAN_LOG: u8 = 7;
+// Block lengths and offsets into blocks are stored in 16 bits
+const MAX_BLOCK_VALUES: usize = 1 << 15;
+// Symbols are numbered in 12 bits, with 0xFFF marking a value
+const MAX_SYMBOLS: usize = 0xFFF;
+// How many values a symbol may stand for
+const MAX_SYMBOL_VALUES: usize = 256;
+// Pairs seen fewer times than this aren't worth a symbol
+const MIN_PAIR_COUNT: usize = 8;
+
+// A child position by its key, or for one with other material, its value
+const ZEROING: u32 = 1 << 31;
+const OTHER_MATERIAL: u32 = 1 << 30;
+
+// Every placement of an ending's pieces with either side to move, solved
+struct Ending {
+    name: String,
+    pieces: Vec<Piece>,
+    legal: Vec<bool>,
+    mated: Vec<bool>,
+    // For the side to move, -2 to 2 as the tables have it
+    wdl: Vec<i32>,
+    // Plies to the next capture, pawn move or mate, 0 for draws
+    dtz: Vec<i32>,
+}
+
+impl Ending {
+    fn parse(name: &str) -> Ending {
+        let (white, black) = name.split_once('v').unwrap();
+        let side = |owner: Owner, pieces: &str| {
+            pieces.chars().map(move |c| Piece::new(owner, PieceType::from_char(c).unwrap())).collect::<Vec<_>>()
+        };
+        let pieces = [side(Owner::White, white), side(Owner::Black, black)].concat();
+        for (i, piece) in pieces.iter().enumerate() {
+            assert!(!pieces[..i].contains(piece), "{} has two of a piece", name);
+        }
+        let count = 2 << (6 * pieces.len());
+        Ending {
+            name: name.to_string(),
+            pieces,
+            legal: vec![false; count],
+            mated: vec![false; count],
+            wdl: vec![0; count],
+            dtz: vec![0; count],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.legal.len()
+    }
+
+    // The key counts the squares of the pieces in order, then the side to move
+    fn key(&self, position: &Position) -> usize {
+        let key = self.pieces.iter().fold(0, |key, piece| {
+            let square = squares(position.bitboard(piece.owner, piece.piece_type)).next().unwrap();
+            key * 64 + square.index()
+        });
+        key * 2 + (position.turn == Owner::Black) as usize
+    }
+
+    fn position(&self, key: usize) -> Option<Position> {
+        let mut position = Position::empty();
+        position.turn = if key & 1 == 0 { Owner::White } else { Owner::Black };
+        let mut rest = key >> 1;
+        for &piece in self.pieces.iter().rev() {
+            let square = Square::from_index(rest % 64);
+            if position.piece_at(square).is_some() {
+                return None;
+            }
+            position.set_piece(square, Some(piece));
+            rest /= 64;
+        }
+        position.refresh_hash();
+        position.validate().ok().map(|_| position)
+    }
+}
+
+// Solve an ending, with the endings its captures and promotions lead to solved already
+fn solve(name: &str, solved: &[Ending]) -> Ending {
+    let mut ending = Ending::parse(name);
+
+    // The children of every position, flattened
+    let mut starts = vec![0; ending.len() + 1];
+    let mut children = Vec::new();
+    for key in 0..ending.len() {
+        if let Some(position) = ending.position(key) {
+            ending.legal[key] = true;
+            let moves = position.legal_moves();
+            ending.mated[key] = moves.is_empty() && position.in_check();
+            for mv in moves {
+                let mut child = position;
+                child.apply_move(mv);
+                let zeroing = if is_zeroing(&position, mv) { ZEROING } else { 0 };
+                let material = material_key(&child);
+                let code = if material == ending.name {
+                    ending.key(&child) as u32
+                } else if child.occupied().count_ones() == 2 {
+                    OTHER_MATERIAL | 2
+                } else {
+                    let other = solved.iter().find(|other| other.name == material).unwrap();
+                    OTHER_MATERIAL | (other.wdl[other.key(&child)] + 2) as u32
+                };
+                children.push(code | zeroing);
+            }
+        }
+        starts[key + 1] = children.len();
+    }
+    let children_of = |key: usize| &children[starts[key]..starts[key + 1]];
+
+    // Win, loss or draw, going back from the mates one ply at a time until nothing changes
+    let mut resolved: Vec<bool> = (0..ending.len()).map(|key| ending.legal[key] && children_of(key).is_empty()).collect();
+    for key in (0..ending.len()).filter(|&key| ending.mated[key]) {
+        ending.wdl[key] = -2;
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for key in 0..ending.len() {
+            if !ending.legal[key] || resolved[key] {
+                continue;
+            }
+            let mut win = false;
+            let mut all_won = true;
+            for &code in children_of(key) {
+                let child = if code & OTHER_MATERIAL != 0 {
+                    Some((code & 0xFF) as i32 - 2)
+                } else {
+                    let child = (code & !ZEROING) as usize;
+                    resolved[child].then_some(ending.wdl[child])
+                };
+                win |= child == Some(-2);
+                all_won &= child == Some(2);
+            }
+            if win || all_won {
+                ending.wdl[key] = if win { 2 } else { -2 };
+                resolved[key] = true;
+                changed = true;
+            }
+        }
+    }
+
+    // The DTZ, again going back one ply at a time. A win takes the move to
+    // the shortest loss, a loss holds out longest, and zeroing moves and
+    // mates count as one ply.
+    let mut resolved: Vec<bool> = (0..ending.len()).map(|key| !ending.legal[key] || ending.wdl[key] == 0).collect();
+    for key in (0..ending.len()).filter(|&key| ending.mated[key]) {
+        ending.dtz[key] = -1;
+        resolved[key] = true;
+    }
+    let mut plies = 1;
+    while resolved.iter().any(|&done| !done) {
+        assert!(plies < 1000, "{} has positions with no DTZ", name);
+        for key in 0..ending.len() {
+            if resolved[key] {
+                continue;
+            }
+            let values = children_of(key).iter().map(|&code| {
+                let child = (code & !(ZEROING | OTHER_MATERIAL)) as usize;
+                if code & OTHER_MATERIAL != 0 {
+                    Some((code & 0xFF == 0, 1))
+                } else if code & ZEROING != 0 || ending.mated[child] {
+                    Some((ending.wdl[child] == -2, 1))
+                } else {
+                    resolved[child].then_some((ending.wdl[child] == -2, ending.dtz[child].abs() + 1))
+                }
+            });
+            let done = if ending.wdl[key] == 2 {
+                // (lost, plies) of the children, keeping the losses for the other side
+                values.flatten().any(|(lost, value)| lost && value == plies)
+            } else {
+                values.map(|value| value.map(|(_, value)| value)).collect::<Option<Vec<_>>>().and_then(|values| values.into_iter().max()) == Some(plies)
+            };
+            if done {
+                ending.dtz[key] = plies * ending.wdl[key].signum();
+                resolved[key] = true;
+            }
+        }
+        plies += 1;
+    }
+    ending
+}
+
+// One of a file's tables, compressed
+#[derive(Default)]
+struct Compressed {
+    flags: u8,
+    single_value: Option<u16>,
+    min_len: u8,
+    max_len: u8,
+    lowest: Vec<u16>,
+    // Each symbol's left and right, or its value and 0xFFF
+    btree: Vec<(u16, u16)>,
+    sparse_index: Vec<(u32, u16)>,
+    block_lengths: Vec<u16>,
+    blocks: Vec<u8>,
+}
+
+// Pair up values, then symbols, until no pair is common enough, then give
+// each symbol left a canonical Huffman code and fill blocks with them
+fn compress(values: &[u16], flags: u8) -> Compressed {
+    if values.iter().all(|&value| value == values[0]) {
+        return Compressed { flags: flags | SINGLE_VALUE, single_value: Some(values[0]), ..Compressed::default() };
+    }
+
+    let mut leaves = values.to_vec();
+    leaves.sort();
+    leaves.dedup();
+    let mut btree: Vec<(usize, usize)> = leaves.iter().map(|&value| (value as usize, 0xFFF)).collect();
+    let mut lengths = vec![1; btree.len()];
+    let mut stream: Vec<usize> = values.iter().map(|value| leaves.binary_search(value).unwrap()).collect();
+
+    while btree.len() < MAX_SYMBOLS {
+        let mut counts = HashMap::new();
+        for pair in stream.windows(2) {
+            if lengths[pair[0]] + lengths[pair[1]] <= MAX_SYMBOL_VALUES {
+                *counts.entry((pair[0], pair[1])).or_insert(0) += 1;
+            }
+        }
+        let Some((pair, count)) = counts.into_iter().max_by_key(|&(pair, count)| (count, Reverse(pair))) else {
+            break;
+        };
+        if count < MIN_PAIR_COUNT {
+            break;
+        }
+        let symbol = btree.len();
+        btree.push(pair);
+        lengths.push(lengths[pair.0] + lengths[pair.1]);
+
+        let mut paired = Vec::with_capacity(stream.len());
+        let mut i = 0;
+        while i < stream.len() {
+            if i + 1 < stream.len() && (stream[i], stream[i + 1]) == pair {
+                paired.push(symbol);
+                i += 2;
+            } else {
+                paired.push(stream[i]);
+                i += 1;
+            }
+        }
+        stream = paired;
+    }
+
+    // Huffman code lengths, by merging the two rarest symbols or subtrees
+    let mut counts = vec![0; btree.len()];
+    for &symbol in &stream {
+        counts[symbol] += 1;
+    }
+    let used: Vec<usize> = (0..btree.len()).filter(|&symbol| counts[symbol] > 0).collect();
+    let mut code_len = vec![0; btree.len()];
+    if used.len() == 1 {
+        code_len[used[0]] = 1;
+    } else {
+        let mut parent = vec![0; 2 * used.len()];
+        let mut heap: BinaryHeap<_> = used.iter().enumerate().map(|(node, &symbol)| Reverse((counts[symbol], node))).collect();
+        let mut next = used.len();
+        while heap.len() > 1 {
+            let Reverse((count1, node1)) = heap.pop().unwrap();
+            let Reverse((count2, node2)) = heap.pop().unwrap();
+            parent[node1] = next;
+            parent[node2] = next;
+            heap.push(Reverse((count1 + count2, next)));
+            next += 1;
+        }
+        let root = next - 1;
+        for (node, &symbol) in used.iter().enumerate() {
+            let mut at = node;
+            while at != root {
+                at = parent[at];
+                code_len[symbol] += 1;
+            }
+        }
+    }
+    let min_len = used.iter().map(|&symbol| code_len[symbol]).min().unwrap();
+    let max_len = used.iter().map(|&symbol| code_len[symbol]).max().unwrap();
+    assert!(max_len <= 32, "Huffman codes longer than 32 bits");
+
+    // The longest codes get the lowest symbol numbers, and symbols that only
+    // appear inside others come last
+    let mut order = used.clone();
+    order.sort_by_key(|&symbol| (Reverse(code_len[symbol]), symbol));
+    order.extend((0..btree.len()).filter(|&symbol| counts[symbol] == 0));
+    let mut number = vec![0; btree.len()];
+    for (n, &symbol) in order.iter().enumerate() {
+        number[symbol] = n;
+    }
+
+    let mut with_len = vec![0u64; max_len + 1];
+    for &symbol in &used {
+        with_len[code_len[symbol]] += 1;
+    }
+    let mut lowest = vec![0u64; max_len + 1];
+    let mut base = vec![0u64; max_len + 1];
+    for len in (min_len..max_len).rev() {
+        lowest[len] = lowest[len + 1] + with_len[len + 1];
+        assert_eq!((base[len + 1] + with_len[len + 1]) % 2, 0, "Huffman code isn't complete");
+        base[len] = (base[len + 1] + with_len[len + 1]) / 2;
+    }
+    if used.len() > 1 {
+        assert_eq!(base[min_len] + with_len[min_len], 1 << min_len, "Huffman code isn't complete");
+    }
+    let code = |symbol: usize| base[code_len[symbol]] + number[symbol] as u64 - lowest[code_len[symbol]];
+
+    // Fill the blocks with whole symbols, the bits going in highest first
+    let block_bits = 8 << BLOCK_SIZE_LOG;
+    let mut blocks: Vec<u8> = Vec::new();
+    let mut block_starts = Vec::new();
+    let mut block_lengths = Vec::new();
+    let mut bits = block_bits;
+    let mut block_values = 0;
+    let mut start = 0;
+    for &symbol in &stream {
+        let len = code_len[symbol];
+        if bits + len > block_bits || block_values + lengths[symbol] > MAX_BLOCK_VALUES {
+            if !block_starts.is_empty() {
+                block_lengths.push((block_values - 1) as u16);
+            }
+            block_starts.push(start);
+            blocks.resize(blocks.len() + (1 << BLOCK_SIZE_LOG), 0);
+            bits = 0;
+            block_values = 0;
+        }
+        let block = blocks.len() - (1 << BLOCK_SIZE_LOG);
+        for bit in (0..len).rev() {
+            if code(symbol) >> bit & 1 != 0 {
+                blocks[block + bits / 8] |= 0x80 >> (bits % 8);
+            }
+            bits += 1;
+        }
+        block_values += lengths[symbol];
+        start += lengths[symbol];
+    }
+    block_lengths.push((block_values - 1) as u16);
+
+    // Which block holds the value in the middle of each span, and where in it
+    let span = 1 << SPAN_LOG;
+    let sparse_index = (0..values.len().div_ceil(span))
+        .map(|k| {
+            let middle = k * span + span / 2;
+            let block = block_starts.partition_point(|&start| start <= middle.min(values.len() - 1)) - 1;
+            let offset = middle - block_starts[block];
+            (block as u32, u16::try_from(offset).unwrap())
+        })
+        .collect();
+
+    Compressed {
+        flags,
+        single_value: None,
+        min_len: min_len as u8,
+        max_len: max_len as u8,
+        lowest: (min_len..=max_len).map(|len| lowest[len] as u16).collect(),
+        btree: order
+            .iter()
+            .map(|&symbol| match btree[symbol] {
+                (value, 0xFFF) => (value as u16, 0xFFF),
+                (left, right) => (number[left] as u16, number[right] as u16),
+            })
+            .collect(),
+        sparse_index,
+        block_lengths,
+        blocks,
+    }
+}
+
+// The file of a solved ending, with `dtz_flags` saying which side to move the
+// DTZ file holds and how
+fn write_table(ending: &Ending, kind: Kind, dtz_flags: u8) -> Vec<u8> {
+    let mut table = Table::new(kind, &ending.name, Bytes::default()).unwrap();
+    let mut pieces: Vec<u8> = ending.pieces.iter().map(|piece| tb_piece(piece.owner, piece.piece_type)).collect();
+    // The leading pawn comes first
+    pieces.sort_by_key(|&piece| piece != W_PAWN);
+    let flags = if kind == Kind::Dtz { dtz_flags } else { 0 };
+
+    let mut items = Vec::new();
+    for file in 0..=table.max_file() {
+        for side in 0..table.sides() {
+            let item = table.item(side, file);
+            table.items[item].pieces[..pieces.len()].copy_from_slice(&pieces);
+            table.items[item].flags = flags;
+            table.set_groups(item, &[0, 0xF], file);
+            items.push(item);
+        }
+    }
+
+    // The value of every position the file holds, by where the reader looks for it
+    let mut values: Vec<Vec<Option<u16>>> = (0..8)
+        .map(|item| {
+            let d = &table.items[item];
+            let size = d.group_idx[d.group_len.iter().position(|&len| len == 0).unwrap()];
+            vec![None; if items.contains(&item) { size as usize } else { 0 }]
+        })
+        .collect();
+    let mut dtz_maps: Vec<[Vec<u16>; 4]> = vec![Default::default(); 8];
+    let mut entries = Vec::new();
+    for key in (0..ending.len()).filter(|&key| ending.legal[key]) {
+        let position = ending.position(key).unwrap();
+        let wdl = ending.wdl[key];
+        if kind == Kind::Dtz && wdl == 0 {
+            continue;
+        }
+        let Some((item, _, idx)) = table.encode(&position) else {
+            continue;
+        };
+        let value = if kind == Kind::Wdl {
+            (wdl + 2) as u16
+        } else {
+            let plies = if wdl > 0 { flags & WIN_PLIES } else { flags & LOSS_PLIES } != 0;
+            let dtz = ending.dtz[key].unsigned_abs() as u16 - 1;
+            let value = if plies { dtz } else { dtz / 2 };
+            if flags & MAPPED != 0 {
+                dtz_maps[item][if wdl > 0 { 0 } else { 1 }].push(value);
+            }
+            value
+        };
+        entries.push((item, idx as usize, wdl, value));
+    }
+    for maps in dtz_maps.iter_mut() {
+        for map in maps.iter_mut() {
+            map.sort();
+            map.dedup();
+        }
+    }
+    for (item, idx, wdl, value) in entries {
+        let value = if flags & MAPPED != 0 {
+            dtz_maps[item][if wdl > 0 { 0 } else { 1 }].binary_search(&value).unwrap() as u16
+        } else {
+            value
+        };
+        let stored = &mut values[item][idx];
+        assert!(stored.is_none_or(|stored| stored == value), "{} has two values at {} of table {}", ending.name, idx, item);
+        *stored = Some(value);
+    }
+
+    // Indices no position needs take the value before them, which compresses best
+    let compressed: Vec<Compressed> = values
+        .iter()
+        .map(|values| {
+            let mut last = values.iter().flatten().next().copied().unwrap_or(0);
+            let filled: Vec<u16> = values
+                .iter()
+                .map(|value| {
+                    last = value.unwrap_or(last);
+                    last
+                })
+                .collect();
+            if filled.is_empty() {
+                Compressed::default()
+            } else {
+                compress(&filled, flags)
+            }
+        })
+        .collect();
+
+    let mut bytes = if kind == Kind::Wdl { WDL_MAGIC } else { DTZ_MAGIC }.to_vec();
+    bytes.push(if table.key != table.key2 { SPLIT } else { 0 } | if table.has_pawns { HAS_PAWNS } else { 0 });
+    for _ in 0..=table.max_file() {
+        bytes.push(0);
+        bytes.extend(pieces.iter().map(|&piece| piece | piece << 4));
+    }
+    bytes.resize(bytes.len() + bytes.len() % 2, 0);
+
+    for &item in &items {
+        let d = &compressed[item];
+        bytes.push(d.flags);
+        if let Some(value) = d.single_value {
+            bytes.push(value as u8);
+            continue;
+        }
+        bytes.extend([BLOCK_SIZE_LOG, SPAN_LOG, 0]);
+        bytes.extend((d.block_lengths.len() as u32).to_le_bytes());
+        bytes.extend([d.max_len, d.min_len]);
+        bytes.extend(d.lowest.iter().flat_map(|lowest| lowest.to_le_bytes()));
+        bytes.extend((d.btree.len() as u16).to_le_bytes());
+        for &(left, right) in &d.btree {
+            bytes.extend([left as u8, (left >> 8) as u8 | (right << 4) as u8, (right >> 4) as u8]);
+        }
+        bytes.resize(bytes.len() + d.btree.len() % 2, 0);
+    }
+
+    if kind == Kind::Dtz {
+        for &item in &items {
+            if compressed[item].flags & MAPPED == 0 {
+                continue;
+            }
+            if flags & WIDE != 0 {
+                bytes.resize(bytes.len() + bytes.len() % 2, 0);
+                for map in &dtz_maps[item] {
+                    bytes.extend((map.len() as u16).to_le_bytes());
+                    bytes.extend(map.iter().flat_map(|value| value.to_le_bytes()));
+                }
+            } else {
+                for map in &dtz_maps[item] {
+                    bytes.push(u8::try_from(map.len()).unwrap());
+                    bytes.extend(map.iter().map(|&value| u8::try_from(value).unwrap()));
+                }
+            }
+        }
+        bytes.resize(bytes.len() + bytes.len() % 2, 0);
+    }
+
+    for &item in &items {
+        for &(block, offset) in &compressed[item].sparse_index {
+            bytes.extend(block.to_le_bytes());
+            bytes.extend(offset.to_le_bytes());
+        }
+    }
+    for &item in &items {
+        bytes.extend(compressed[item].block_lengths.iter().flat_map(|length| length.to_le_bytes()));
+    }
+    for &item in &items {
+        bytes.resize((bytes.len() + 0x3F) & !0x3F, 0);
+        bytes.extend(&compressed[item].blocks);
+    }
+    bytes
+}
+
+// The same position with the colours swapped and the board turned upside down
+fn swap_colours(position: &Position) -> Position {
+    let mut swapped = Position::empty();
+    for square in squares(position.occupied()) {
+        let piece = position.piece_at(square).unwrap();
+        swapped.set_piece(Square::new(square.col(), 7 - square.row()), Some(Piece::new(piece.owner.opponent(), piece.piece_type)));
+    }
+    swapped.turn = position.turn.opponent();
+    swapped.refresh_hash();
+    swapped
+}
+
+// Probe every position of the ending from the files written, both ways round.
+// DTZ values rounded to moves may come back a ply short.
+fn check(tablebases: &Tablebases, ending: &Ending, dtz_flags: u8) {
+    for key in (0..ending.len()).filter(|&key| ending.legal[key]) {
+        let position = ending.position(key).unwrap();
+        let wdl = ending.wdl[key];
+        let dtz = ending.dtz[key];
+        let exact = if wdl > 0 { dtz_flags & WIN_PLIES } else { dtz_flags & LOSS_PLIES } != 0;
+        for position in [position, swap_colours(&position)] {
+            let probe = tablebases.probe(&position).unwrap();
+            let fen = position.to_fen();
+            assert_eq!(probe.wdl, Wdl::from_score(wdl), "{}", fen);
+            assert!(probe.dtz == dtz || (!exact && probe.dtz == dtz - dtz.signum()), "{} has DTZ {}, not {}", fen, probe.dtz, dtz);
+        }
+    }
+}
+
+pub fn write_test_tables(directory: &Path) -> io::Result<()> {
+    let mut solved: Vec<Ending> = Vec::new();
+    for (name, side, flags) in ENDINGS {
+        let ending = solve(name, &solved);
+        let flags = if side == Owner::Black { flags | STM } else { flags };
+        fs::write(directory.join(format!("{}.rtbw", name)), write_table(&ending, Kind::Wdl, flags))?;
+        fs::write(directory.join(format!("{}.rtbz", name)), write_table(&ending, Kind::Dtz, flags))?;
+
+        let longest = (0..ending.len()).max_by_key(|&key| (ending.dtz[key], Reverse(key))).unwrap();
+        println!("{}: longest win {} plies, {}", name, ending.dtz[longest], ending.position(longest).map(|position| position.to_fen()).unwrap_or_default());
+        solved.push(ending);
+    }
+
+    let tablebases = Tablebases::open(directory)?;
+    for (ending, (_, side, flags)) in solved.iter().zip(ENDINGS) {
+        check(&tablebases, ending, if side == Owner::Black { flags | STM } else { flags });
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::mem;
+
+use crate::board::movegen::Move;
+
+// What a stored score says about the real one, after alpha-beta cut the search short
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // The real score is at least this (the search failed high)
+    Lower,
+    // The real score is at most this (no move reached alpha)
+    Upper,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Entry {
+    // Full Zobrist hash, to tell positions sharing a slot apart
+    pub key: u64,
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+// Search results by position hash, in a fixed amount of memory. Each hash
+// has one slot, and a new result replaces an old one unless the old one is
+// for the same position and was searched deeper.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let slots = (megabytes * 1024 * 1024 / mem::size_of::<Option<Entry>>()).max(1);
+        // A power of two, so the slot is just the low bits of the hash
+        let slots = 1 << (usize::BITS - 1 - slots.leading_zeros());
+        TranspositionTable { entries: vec![None; slots] }
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[self.slot(key)].filter(|entry| entry.key == key)
+    }
+
+    pub fn store(&mut self, entry: Entry) {
+        let slot = self.slot(entry.key);
+        match self.entries[slot] {
+            Some(old) if old.key == entry.key && old.depth > entry.depth => {}
+            _ => self.entries[slot] = Some(entry),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Per mille of slots in use, as UCI reports it in `hashfull`
+    pub fn usage(&self) -> usize {
+        let sample = self.entries.len().min(1000);
+        self.entries[..sample].iter().filter(|entry| entry.is_some()).count() * 1000 / sample
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(key: u64, depth: u32) -> Entry {
+        Entry { key, depth, score: 10, bound: Bound::Exact, best_move: None }
+    }
+
+    #[test]
+    fn test_size() {
+        let table = TranspositionTable::new(1);
+        assert!(table.capacity().is_power_of_two());
+        assert!(table.capacity() * mem::size_of::<Option<Entry>>() <= 1024 * 1024);
+    }
+
+    #[test]
+    fn test_replacement() {
+        let mut table = TranspositionTable::new(1);
+        let other_key = 7 + table.capacity() as u64;
+
+        table.store(entry(7, 5));
+        assert_eq!(table.probe(7), Some(entry(7, 5)));
+        assert_eq!(table.probe(other_key), None);
+
+        // A shallower search of the same position keeps the deeper result...
+        table.store(entry(7, 2));
+        assert_eq!(table.probe(7).unwrap().depth, 5);
+        // ...but another position in the same slot replaces it
+        table.store(entry(other_key, 1));
+        assert_eq!(table.probe(7), None);
+        assert_eq!(table.probe(other_key).unwrap().depth, 1);
+    }
+}
@@ -0,0 +1,385 @@
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board::movegen::Move;
+use crate::board::piece::Owner;
+use crate::board::position::Position;
+use crate::clock::{think_time, DEFAULT_MOVES_TO_GO};
+use crate::engine::search::{search, SearchInfo, SearchLimits};
+use crate::engine::syzygy::Tablebases;
+use crate::engine::tt::TranspositionTable;
+
+// Transposition table size in MB, unless the GUI sets the Hash option
+const DEFAULT_HASH: usize = 16;
+const MAX_HASH: usize = 1024;
+
+// The clock parts of a `go` command
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct GoClock {
+    time: [Option<Duration>; 2],
+    increment: [Duration; 2],
+    moves_to_go: Option<u32>,
+}
+
+impl GoClock {
+    fn movetime(&self, turn: Owner) -> Option<Duration> {
+        let time = self.time[turn as usize]?;
+        let moves_to_go = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO);
+        Some(think_time(time, self.increment[turn as usize], moves_to_go))
+    }
+}
+
+// `go` without any limits searches until told to stop
+fn parse_go(args: &[&str], turn: Owner) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut clock = GoClock::default();
+
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        let mut value = || args.next().and_then(|v| v.parse::<u64>().ok());
+        match arg {
+            "depth" => limits.depth = value().map(|depth| depth as u32),
+            "movetime" => limits.movetime = value().map(Duration::from_millis),
+            "wtime" => clock.time[Owner::White as usize] = value().map(Duration::from_millis),
+            "btime" => clock.time[Owner::Black as usize] = value().map(Duration::from_millis),
+            "winc" => clock.increment[Owner::White as usize] = Duration::from_millis(value().unwrap_or(0)),
+            "binc" => clock.increment[Owner::Black as usize] = Duration::from_millis(value().unwrap_or(0)),
+            "movestogo" => clock.moves_to_go = value().map(|moves| moves as u32),
+            _ => {}
+        }
+    }
+
+    if limits.movetime.is_none() {
+        limits.movetime = clock.movetime(turn);
+    }
+    limits
+}
+
+fn info_line(info: &SearchInfo) -> String {
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let millis = info.time.as_millis() as u64;
+    let nps = info.nodes * 1000 / millis.max(1);
+    let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_uci()).collect();
+
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        score,
+        info.nodes,
+        nps,
+        millis,
+        pv.join(" ")
+    )
+}
+
+fn send(output: &Mutex<impl Write>, line: &str) {
+    let mut output = output.lock().unwrap();
+    // The GUI going away ends the session on the next read anyway
+    let _ = writeln!(output, "{}", line);
+    let _ = output.flush();
+}
+
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl RunningSearch {
+    // Ends the search and waits for its `bestmove`, so it never arrives out of order
+    fn finish(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
+// The position the GUI set up, with the positions before it for spotting repetitions
+struct Game {
+    position: Position,
+    history: Vec<Position>,
+}
+
+// `setoption name Hash value <MB>`
+fn parse_hash_option(args: &[&str]) -> Option<usize> {
+    match args {
+        ["name", name, "value", value] if name.eq_ignore_ascii_case("hash") => value.parse().ok(),
+        _ => None,
+    }
+}
+
+// `setoption name SyzygyPath value <directory>`, where the directory may have
+// spaces in it. An empty one, or "<empty>", turns the tablebases off.
+fn parse_syzygy_option(args: &[&str]) -> Option<String> {
+    match args {
+        ["name", name, "value", directory @ ..] if name.eq_ignore_ascii_case("syzygypath") => {
+            Some(directory.join(" ").replace("<empty>", ""))
+        }
+        _ => None,
+    }
+}
+
+// `position [startpos | fen <fen>] [moves <move>...]`
+fn parse_position(args: &[&str]) -> Result<Game, String> {
+    let moves_at = args.iter().position(|&arg| arg == "moves").unwrap_or(args.len());
+    let position = match args.first() {
+        Some(&"startpos") => Position::default(),
+        Some(&"fen") => Position::from_fen(&args[1..moves_at].join(" ")).map_err(|e| e.to_string())?,
+        _ => return Err("expected startpos or fen".to_string()),
+    };
+
+    let mut game = Game { position, history: Vec::new() };
+    for text in args.iter().skip(moves_at + 1) {
+        let mv = game.position.parse_uci(text).ok_or(format!("illegal move {}", text))?;
+        game.history.push(game.position);
+        game.position.apply_move(mv);
+    }
+    Ok(game)
+}
+
+// Speak UCI on `input` and `output` until told to quit or the input ends.
+// Searches run on their own thread so `stop` can be read while they think.
+pub fn run(input: impl BufRead, output: impl Write + Send + 'static) -> io::Result<()> {
+    let output = Arc::new(Mutex::new(output));
+    let mut game = Game { position: Position::default(), history: Vec::new() };
+    let mut running: Option<RunningSearch> = None;
+    let mut table = Arc::new(Mutex::new(TranspositionTable::new(DEFAULT_HASH)));
+    let mut tablebases: Option<Arc<Tablebases>> = None;
+
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (&command, args) = match words.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+
+        match command {
+            "uci" => {
+                send(&output, "id name Rusty Chess");
+                send(&output, "id author the Rusty Chess developers");
+                send(&output, &format!("option name Hash type spin default {} min 1 max {}", DEFAULT_HASH, MAX_HASH));
+                send(&output, "option name SyzygyPath type string default <empty>");
+                send(&output, "uciok");
+            }
+            "isready" => send(&output, "readyok"),
+            "ucinewgame" => {
+                if let Some(search) = running.take() {
+                    search.finish();
+                }
+                game = Game { position: Position::default(), history: Vec::new() };
+                table.lock().unwrap().clear();
+            }
+            "setoption" => {
+                if let Some(megabytes) = parse_hash_option(args) {
+                    if let Some(search) = running.take() {
+                        search.finish();
+                    }
+                    table = Arc::new(Mutex::new(TranspositionTable::new(megabytes.clamp(1, MAX_HASH))));
+                }
+                if let Some(directory) = parse_syzygy_option(args) {
+                    tablebases = None;
+                    if !directory.is_empty() {
+                        match Tablebases::open(Path::new(&directory)) {
+                            Ok(found) => {
+                                send(&output, &format!("info string found {} tablebases, up to {} pieces", found.len(), found.max_pieces()));
+                                tablebases = Some(Arc::new(found));
+                            }
+                            Err(e) => send(&output, &format!("info string no tablebases: {}", e)),
+                        }
+                    }
+                }
+            }
+            "position" => match parse_position(args) {
+                Ok(new_game) => game = new_game,
+                Err(e) => send(&output, &format!("info string invalid position: {}", e)),
+            },
+            "go" => {
+                if let Some(search) = running.take() {
+                    search.finish();
+                }
+
+                let limits = parse_go(args, game.position.turn);
+                let infinite = limits.depth.is_none() && limits.movetime.is_none();
+                let stop = Arc::new(AtomicBool::new(false));
+                let (position, history) = (game.position, game.history.clone());
+                let (thread_stop, thread_output, thread_table) = (Arc::clone(&stop), Arc::clone(&output), Arc::clone(&table));
+                let thread_tablebases = tablebases.clone();
+
+                let handle = thread::spawn(move || {
+                    // In the tablebases there is nothing to search for
+                    let best = match thread_tablebases.and_then(|tablebases| tablebases.best_move(&position)) {
+                        Some((mv, probe)) => {
+                            send(&thread_output, &format!("info string {}", probe));
+                            Some(mv)
+                        }
+                        None => {
+                            let mut table = thread_table.lock().unwrap();
+                            search(&position, &history, limits, &mut table, &thread_stop, |info| {
+                                send(&thread_output, &info_line(info));
+                            })
+                            .best_move()
+                        }
+                    };
+                    // An infinite search answers only once told to stop, even
+                    // when it found a mate or ran out of depth long before
+                    while infinite && !thread_stop.load(Ordering::Relaxed) {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                    // UCI wants a move even when there is none, "0000" is the null move
+                    let best = best.map_or("0000".to_string(), Move::to_uci);
+                    send(&thread_output, &format!("bestmove {}", best));
+                });
+                running = Some(RunningSearch { stop, handle });
+            }
+            "stop" => {
+                if let Some(search) = running.take() {
+                    search.finish();
+                }
+            }
+            "quit" => break,
+            // Anything else, including options we don't have, is ignored as the protocol asks
+            _ => {}
+        }
+    }
+
+    if let Some(search) = running.take() {
+        search.finish();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufReader, Read};
+    use std::sync::mpsc::{self, Receiver};
+
+    // Output the test can still read after `run` has taken ownership of it
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn lines(&self) -> Vec<String> {
+            let text = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            text.lines().map(str::to_string).collect()
+        }
+    }
+
+    // Input the test hands over a line at a time, ending when the sender goes
+    struct LineInput {
+        receiver: Receiver<String>,
+        pending: Vec<u8>,
+    }
+
+    impl Read for LineInput {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pending.is_empty() {
+                match self.receiver.recv() {
+                    Ok(line) => self.pending = format!("{}\n", line).into_bytes(),
+                    Err(_) => return Ok(0),
+                }
+            }
+            let len = buf.len().min(self.pending.len());
+            buf[..len].copy_from_slice(&self.pending[..len]);
+            self.pending.drain(..len);
+            Ok(len)
+        }
+    }
+
+    fn session(input: &str) -> Vec<String> {
+        let output = SharedBuffer::default();
+        run(input.as_bytes(), output.clone()).unwrap();
+        output.lines()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let lines = session("uci\nisready\nquit\n");
+        assert_eq!(lines.last().unwrap(), "readyok");
+        assert!(lines.contains(&"uciok".to_string()));
+    }
+
+    #[test]
+    fn test_go_depth() {
+        let lines = session("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 2\n");
+        assert!(lines.iter().any(|line| line.starts_with("info depth 1 ")));
+        assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn test_go_depth_0_still_moves() {
+        let lines = session("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 0\n");
+        assert!(lines.iter().any(|line| line.starts_with("info depth 1 ")));
+        let best = lines.last().unwrap();
+        assert!(best.starts_with("bestmove ") && best != "bestmove 0000", "{}", best);
+    }
+
+    #[test]
+    fn test_go_infinite_waits_for_stop() {
+        let (sender, receiver) = mpsc::channel();
+        let output = SharedBuffer::default();
+        let session_output = output.clone();
+        let session = thread::spawn(move || {
+            let input = BufReader::new(LineInput { receiver, pending: Vec::new() });
+            run(input, session_output).unwrap();
+        });
+
+        // The mate is found at once, but the move has to wait
+        sender.send("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string()).unwrap();
+        sender.send("go infinite".to_string()).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert!(output.lines().iter().any(|line| line.starts_with("info depth 1 ")));
+        assert!(!output.lines().iter().any(|line| line.starts_with("bestmove")));
+
+        sender.send("stop".to_string()).unwrap();
+        sender.send("isready".to_string()).unwrap();
+        drop(sender);
+        session.join().unwrap();
+        let lines = output.lines();
+        assert_eq!(lines[lines.len() - 2..], ["bestmove a1a8", "readyok"]);
+    }
+
+    #[test]
+    fn test_position_moves() {
+        let game = parse_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"]).unwrap();
+        assert_eq!(game.position.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        assert_eq!(game.history.len(), 3);
+        assert!(parse_position(&["startpos", "moves", "e2e5"]).is_err());
+    }
+
+    #[test]
+    fn test_hash_option() {
+        assert_eq!(parse_hash_option(&["name", "Hash", "value", "64"]), Some(64));
+        assert_eq!(parse_hash_option(&["name", "Threads", "value", "4"]), None);
+    }
+
+    #[test]
+    fn test_syzygy_option() {
+        assert_eq!(parse_syzygy_option(&["name", "SyzygyPath", "value", "/my", "tables"]), Some("/my tables".to_string()));
+        assert_eq!(parse_syzygy_option(&["name", "SyzygyPath", "value", "<empty>"]), Some(String::new()));
+        assert_eq!(parse_syzygy_option(&["name", "Hash", "value", "64"]), None);
+    }
+
+    #[test]
+    fn test_clock_time() {
+        let limits = parse_go(&["wtime", "60000", "btime", "1000", "winc", "1000"], Owner::White);
+        assert_eq!(limits.movetime, Some(Duration::from_millis(2750)));
+        assert_eq!(parse_go(&["depth", "4"], Owner::Black), SearchLimits { depth: Some(4), movetime: None });
+    }
+}
@@ -1,183 +1,1199 @@
-use crate::board::space::Space;
-use crate::board::piece::{Piece, PieceType};
+use rusty_chess::board::bitboard::squares;
+use rusty_chess::board::eco::EcoTable;
+use rusty_chess::board::movegen::{Move, PROMOTION_CHOICES};
+use rusty_chess::board::outcome::GameResult;
+use rusty_chess::board::pgn::Pgn;
+use rusty_chess::board::piece::{Owner, Piece, PieceType};
+use rusty_chess::board::position::Position;
+use rusty_chess::board::square::Square;
+use rusty_chess::clock::{think_time, Clock, TimeControl, DEFAULT_MOVES_TO_GO};
+use rusty_chess::engine::book::Book;
+use rusty_chess::engine::search::{SearchLimits, SearchThread};
+use rusty_chess::engine::syzygy::{Probe, ProbeThread, Tablebases};
+use rusty_chess::engine::tt::TranspositionTable;
+use rusty_chess::net::{check_move, Message, ProtocolError};
+use crate::analysis::Analysis;
+use crate::components::button::Button;
+use crate::components::focus::Focus;
+use crate::components::panel::Panel;
+use crate::components::text_box::TextBox;
+use crate::components::widget::Widget;
+use crate::lan_session::Network;
+use crate::theme::PieceSet;
+use raylib::consts::KeyboardKey::*;
 use raylib::consts::MouseButton::*;
 use raylib::prelude::*;
 use std::fmt::{Error};
-use std::ops::Neg;
-use std::collections::HashMap;
-use std::{panic, u8};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+pub const SPACE_SIZE: f32 = 60.0;
+
+// Memory for the computer's transposition table, in MB
+const ENGINE_HASH: usize = 16;
+
+const NEW_GAME_RECT: Rectangle = Rectangle { x: 140.0, y: 270.0, width: 200.0, height: 50.0 };
+
+// The move list fills the right side of the window below the status text and the opening name
+const MOVE_LIST_RECT: Rectangle = Rectangle { x: 500.0, y: 172.0, width: 440.0, height: 352.0 };
+const MOVE_ROW_HEIGHT: f32 = 22.0;
+
+// When analysing, the engine's lines take the top of the move list's place
+const ANALYSIS_LINES_RECT: Rectangle = Rectangle { x: 500.0, y: 172.0, width: 440.0, height: 96.0 };
+const ANALYSIS_MOVE_LIST_RECT: Rectangle = Rectangle { x: 500.0, y: 276.0, width: 440.0, height: 242.0 };
+// In the gap between the board and everything on its right
+const EVAL_BAR_RECT: Rectangle = Rectangle { x: 484.0, y: 0.0, width: 12.0, height: 480.0 };
+const ARROW_COLOR: Color = Color { r: 0, g: 110, b: 200, a: 170 };
+
+// Below the board
+const MOVE_INPUT_RECT: Rectangle = Rectangle { x: 0.0, y: 490.0, width: 480.0, height: 40.0 };
+
+// How long a notice about a shortcut stays up in the status text
+const NOTICE_TIME: Duration = Duration::from_secs(4);
+
+// Laid over the squares of the last move, and the squares the held piece can move to
+const LAST_MOVE_COLOR: Color = Color { r: 255, g: 225, b: 70, a: 110 };
+const MOVE_HINT_COLOR: Color = Color { r: 20, g: 80, b: 30, a: 110 };
+const CHECK_COLOR: Color = Color { r: 255, g: 0, b: 0, a: 220 };
+
+// Clocks sit in the top right corner, black's above white's
+const BLACK_CLOCK_RECT: Rectangle = Rectangle { x: 760.0, y: 20.0, width: 180.0, height: 40.0 };
+const WHITE_CLOCK_RECT: Rectangle = Rectangle { x: 760.0, y: 65.0, width: 180.0, height: 40.0 };
+
+// Under the clocks in a network game
+const RESIGN_RECT: Rectangle = Rectangle { x: 760.0, y: 110.0, width: 85.0, height: 30.0 };
+const DRAW_RECT: Rectangle = Rectangle { x: 855.0, y: 110.0, width: 85.0, height: 30.0 };
+
+fn engine_table() -> Arc<Mutex<TranspositionTable>> {
+    static TABLE: OnceLock<Arc<Mutex<TranspositionTable>>> = OnceLock::new();
+    Arc::clone(TABLE.get_or_init(|| Arc::new(Mutex::new(TranspositionTable::new(ENGINE_HASH)))))
+}
+
+fn openings() -> &'static EcoTable {
+    static OPENINGS: OnceLock<EcoTable> = OnceLock::new();
+    OPENINGS.get_or_init(EcoTable::built_in)
+}
+
+// Which screen the window is showing
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameState {
+    Menu,
+    Settings,
+    Editor,
+    Playing,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    // How long the computer may think, per move
+    pub fn limits(self) -> SearchLimits {
+        match self {
+            Difficulty::Easy => SearchLimits { depth: Some(1), movetime: None },
+            Difficulty::Medium => SearchLimits { depth: Some(3), movetime: Some(Duration::from_secs(2)) },
+            Difficulty::Hard => SearchLimits { depth: None, movetime: Some(Duration::from_secs(3)) },
+        }
+    }
+}
+
+// Who the person at the board is playing against
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Opponent {
+    Human,
+    Computer { colour: Owner, difficulty: Difficulty },
+    // Someone playing `colour` from another machine
+    Remote { colour: Owner },
+}
 
 pub struct Game {
-    pub turn: bool,
-    pub layout: [[Space; 8]; 8],
-    // Map the names of pieces to the number of them left
-    pub state: u8,
+    pub position: Position,
+    pub state: GameState,
+    pub result: Option<GameResult>,
+    // PGN tag pairs (players, date, ...) saved along with the moves
+    pub tags: Vec<(String, String)>,
+    pub opponent: Opponent,
+    // None for an untimed game
+    pub clock: Option<Clock>,
+    // Set when a flag falls or a player resigns or agrees a draw, which ends
+    // the game after the last move of the line
+    declared_result: Option<GameResult>,
+    // The connection to the other player in a game over the network
+    pub network: Option<Network>,
+    // The computer's search for its next move, running on another thread
+    thinking: Option<SearchThread>,
+    // Kept between the computer's moves, a lot of what it worked out still applies.
+    // Every game shares the one table.
+    table: Arc<Mutex<TranspositionTable>>,
+    // Where the computer takes its first moves from, if it has been given one
+    pub book: Option<Rc<Book>>,
+    // Endgame tables it plays perfectly from, if it has been given any
+    pub tablebases: Option<Arc<Tablebases>>,
+    // What the tablebases say about the position shown, by its hash
+    tablebase_probe: Option<(u64, Option<Probe>)>,
+    // The probe of the position shown under way, by its hash
+    probing: Option<(u64, ProbeThread)>,
+    // The engine's view of the position on the board, when analysing instead of playing
+    analysis: Option<Analysis>,
+    // For naming the opening being played
+    openings: &'static EcoTable,
+    // Every position along the line of play, `position` being the one at `ply`.
+    // Moves past `ply` were taken back (or not replayed yet) and can be redone.
+    positions: Vec<Position>,
+    line: Vec<Move>,
+    line_san: Vec<String>,
+    ply: usize,
+    // First row of the move list that is shown
+    move_list_scroll: usize,
     offset: Vector2,
     dragging_piece: Option<Piece>,
     dragging: bool,
-    mouse_coords: (usize, usize)
+    drag_position: Vector2,
+    mouse_square: Square,
+    // Black at the bottom of the screen instead of white
+    flipped: bool,
+    // The piece picked up by clicking or dragging it
+    selected: Option<Square>,
+    // A pawn dropped on the last row, waiting for the player to pick a piece
+    pending_promotion: Option<Move>,
+    move_input: TextBox,
+    // The last text typed in that wasn't a legal move, shown as an error until it is changed
+    rejected_input: Option<String>,
+    // What came of the last shortcut that had something to say, in its colour, and when
+    notice: Option<(String, Color, Instant)>,
+    // Which of the move box and the buttons has the keyboard
+    focus: Focus,
+    // Resign, or Leave while a network game is being set up
+    resign_button: Button,
+    // Offer a draw, or accept the other player's offer
+    draw_button: Button,
+    new_game_button: Button,
 }
 
 impl Game {
 
+    // The square drawn in a place on the screen, or the place a square is
+    // drawn in. Flipping turns the board around, so it works both ways.
+    fn orient(&self, square: Square) -> Square {
+        if self.flipped { square.rotated() } else { square }
+    }
+
+    // Screen area of a space, worked out from its column and row
+    fn space_rect(&self, square: Square) -> Rectangle {
+        let place = self.orient(square);
+        Rectangle {
+            x: place.col() as f32 * SPACE_SIZE,
+            y: place.row() as f32 * SPACE_SIZE,
+            width: SPACE_SIZE,
+            height: SPACE_SIZE
+        }
+    }
+
+    fn space_center(&self, square: Square) -> Vector2 {
+        let rect = self.space_rect(square);
+        Vector2 { x: rect.x + rect.width / 2.0, y: rect.y + rect.height / 2.0 }
+    }
+
     fn get_piece_at(&self, mouse_position: Vector2) -> Option<Piece> {
-        let row = (mouse_position.x / 60.0).floor();
-        let col = (mouse_position.y / 60.0).floor();
+        match self.square_at(mouse_position) {
+            Some(square) => self.position.piece_at(square),
+            None => None,
+        }
+    }
 
-        if row >= 8.0 || col >= 8.0 {
+    // The square under a point on the screen, if it is over the board
+    fn square_at(&self, point: Vector2) -> Option<Square> {
+        let col = (point.x / SPACE_SIZE).floor();
+        let row = (point.y / SPACE_SIZE).floor();
+
+        if !(0.0..8.0).contains(&col) || !(0.0..8.0).contains(&row) {
             return None;
         }
 
-        self.layout[row as usize][col as usize].piece
+        Some(self.orient(Square::new(col as usize, row as usize)))
     }
-    fn to_board_cooridinates(mouse_position: Vector2) -> (usize, usize) {
-        let row = (mouse_position.x / 60.0).floor();
-        let col = (mouse_position.y / 60.0).floor();
 
-        (row as usize, col as usize)
+    fn draw_board(&mut self, d: &mut RaylibDrawHandle) {
+        // The colours go by place on the screen, so they stay put when the board is flipped
+        for place in Square::all() {
+            let color = if (place.col() + place.row()) % 2 == 0 { color::Color::LIGHTGRAY } else { color::Color::GRAY };
+            d.draw_rectangle_rec(self.space_rect(self.orient(place)), color);
+        }
+
+        if self.ply > 0 {
+            let last = self.line[self.ply - 1];
+            d.draw_rectangle_rec(self.space_rect(last.from), LAST_MOVE_COLOR);
+            d.draw_rectangle_rec(self.space_rect(last.to), LAST_MOVE_COLOR);
+        }
+
+        // A king in check glows red from underneath
+        if let Some(king) = self.position.king_square(self.position.turn).filter(|_| self.position.in_check()) {
+            let center = self.space_center(king);
+            d.draw_circle_gradient(center.x as i32, center.y as i32, SPACE_SIZE * 0.6, CHECK_COLOR, Color { a: 0, ..CHECK_COLOR });
+        }
+
+        self.draw_coordinates(d);
     }
 
-    fn draw_board(&mut self, d: &mut RaylibDrawHandle) {
-        for row in 0..8 {
-            for col in 0..8 {
-                d.draw_rectangle(
-                    60 * row,
-                    60 * col,
-                    60,
-                    60,
-                    if (col + row) % 2 == 0 {
-                        color::Color::LIGHTGRAY
-                    } else {
-                        color::Color::GRAY
-                    },
-                );
-            }
-        }
-    }
-
-    fn draw_pieces(&mut self, d: &mut RaylibDrawHandle, textures: &HashMap<PieceType, Vec<Texture2D>>) {
-        for row in 0..self.layout.len() {
-            for col in 0..self.layout[row].len() {
-                let identity = match self.layout[row][col].piece {
-                    Some(p) => p.owner,
-                    None => continue,
-                };
-
-                let p_text = 
-                    &textures
-                    .get(&self.layout[row][col].piece.unwrap().piece_type)
-                    .unwrap()[identity as usize];
-
-                // Draw texture for respective piece type
-                d.draw_texture_pro(
-                    p_text,
-                    Rectangle {
-                        x: 0.0,
-                        y: 0.0,
-                        width: p_text.width() as f32,
-                        height: p_text.height() as f32,
-                    },
-                    self.layout[row][col].rect,
-                    Vector2 { x: 0.0, y: 0.0 },
-                    0.0,
-                    Color::WHITE,
-                );
-            }
-        }
-    }
-
-    pub fn run(&mut self, d: &mut RaylibDrawHandle, thread: RaylibThread, textures: &HashMap<PieceType, Vec<Texture2D>>) -> Result<(), Error> {
-        d.clear_background(Color::WHITE);
-        self.draw_board(d);
-        if d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
-            self.mouse_coords = Game::to_board_cooridinates(d.get_mouse_position());
+    // Files along the bottom edge and ranks along the left one, in the corners
+    // of the squares in the colour of the other kind of square
+    fn draw_coordinates(&self, d: &mut RaylibDrawHandle) {
+        let text_color = |place: Square| if (place.col() + place.row()).is_multiple_of(2) { Color::GRAY } else { Color::LIGHTGRAY };
 
-            self.dragging_piece = match self.get_piece_at(d.get_mouse_position()) {
-                Some(p) => {
-                    self.dragging = true;
-                    self.offset.x = d.get_mouse_x() as f32 - self.layout[self.mouse_coords.0][self.mouse_coords.1].rect.x;
-                    self.offset.y = d.get_mouse_y() as f32 - self.layout[self.mouse_coords.0][self.mouse_coords.1].rect.y;
-                    Some(p)
-                },
-                None => { 
-                    None
-                } 
+        for i in 0..8 {
+            let place = Square::new(i, 7);
+            let file = (b'a' + self.orient(place).col() as u8) as char;
+            let rect = self.space_rect(self.orient(place));
+            d.draw_text(&file.to_string(), (rect.x + rect.width) as i32 - 12, (rect.y + rect.height) as i32 - 18, 16, text_color(place));
+
+            let place = Square::new(0, i);
+            let rank = 8 - self.orient(place).row();
+            let rect = self.space_rect(self.orient(place));
+            d.draw_text(&rank.to_string(), rect.x as i32 + 3, rect.y as i32 + 2, 16, text_color(place));
+        }
+    }
+
+    // A dot on each square the piece can move to, or a ring around the piece it would take
+    fn draw_valid_moves(&self, d: &mut RaylibDrawHandle, from: Square) {
+        // Promotions would mark the same square once per piece to promote to
+        let moves = self.position.legal_moves_from(from).into_iter().filter(|mv| mv.promotion.is_none_or(|p| p == PieceType::Queen));
+
+        for mv in moves {
+            let center = self.space_center(mv.to);
+            if self.position.is_capture(mv) {
+                d.draw_ring(center, SPACE_SIZE * 0.4, SPACE_SIZE * 0.5, 0.0, 360.0, 32, MOVE_HINT_COLOR);
+            } else {
+                d.draw_circle_v(center, SPACE_SIZE * 0.15, MOVE_HINT_COLOR);
+            }
+        }
+    }
+
+    fn draw_pieces(&mut self, d: &mut RaylibDrawHandle, pieces: &PieceSet) {
+        for square in squares(self.position.occupied()) {
+            let piece = match self.position.piece_at(square) {
+                Some(p) => p,
+                None => continue,
             };
-        } else if d.is_mouse_button_released(MOUSE_BUTTON_LEFT) {
-            self.dragging = false;
-            match self.dragging_piece {
-                Some(value) => {
-                    self.layout[self.mouse_coords.0][self.mouse_coords.1].rect.x = value.rect.x;
-                    self.layout[self.mouse_coords.0][self.mouse_coords.1].rect.y = value.rect.y;
-                },
-                None => {
-                    println!("no piece there")
-                } 
+
+            // The piece being dragged follows the mouse instead of its space
+            let mut dest = self.space_rect(square);
+            if self.dragging && square == self.mouse_square {
+                dest.x = self.drag_position.x;
+                dest.y = self.drag_position.y;
             }
-            self.dragging_piece = None;
+
+            pieces.draw(d, piece, dest);
         }
+    }
 
-        if self.dragging {
-            self.layout[self.mouse_coords.0][self.mouse_coords.1].rect.x = d.get_mouse_x() as f32 - self.offset.x;
-            self.layout[self.mouse_coords.0][self.mouse_coords.1].rect.y = d.get_mouse_y() as f32 - self.offset.y;
-        }             
+    // The choices stack from the promotion space back towards the middle of the board
+    fn promotion_rects(&self, mv: Move) -> Vec<(PieceType, Rectangle)> {
+        let step = -self.position.turn.forward() as i32;
+
+        PROMOTION_CHOICES
+            .iter()
+            .enumerate()
+            .map(|(i, &piece_type)| {
+                let row = mv.to.row() as i32 + step * i as i32;
+                (piece_type, self.space_rect(Square::new(mv.to.col(), row as usize)))
+            })
+            .collect()
+    }
+
+    fn draw_promotion_picker(&self, d: &mut RaylibDrawHandle, pieces: &PieceSet, mv: Move) {
+        d.draw_rectangle(0, 0, 480, 480, Color {r: 0, g: 0, b: 0, a: 120 });
+
+        for (piece_type, rect) in self.promotion_rects(mv) {
+            d.draw_rectangle_rec(rect, Color::WHITE);
+            d.draw_rectangle_lines_ex(rect, 2.0, Color::DARKGRAY);
+            pieces.draw(d, Piece::new(self.position.turn, piece_type), rect);
+        }
+    }
+
+    fn draw_status(&self, d: &mut RaylibDrawHandle) {
+        let to_move = match self.position.turn {
+            Owner::White => "White to move",
+            Owner::Black => "Black to move",
+        };
+        d.draw_text(to_move, 500, 20, 24, Color::BLACK);
+
+        if self.result.is_none() && self.position.in_check() {
+            d.draw_text("Check!", 500, 50, 24, Color::RED);
+        }
+
+        if let Some(status) = self.network.as_ref().and_then(|network| network.status.as_ref()) {
+            d.draw_text(status, 500, 90, 20, Color::DARKGRAY);
+        } else if self.thinking.is_some() {
+            d.draw_text("Computer is thinking...", 500, 90, 20, Color::DARKGRAY);
+
+        } else if self.ply < self.line.len() {
+            let browsing = format!("Move {} of {}", self.ply, self.line.len());
+            d.draw_text(&browsing, 500, 90, 20, Color::DARKGRAY);
+            d.draw_text("Left/Right to step, Home/End to jump", 500, 115, 20, Color::DARKGRAY);
+        }
+
+        // A notice goes over the browsing help until it times out
+        if let Some((text, color, shown)) = &self.notice {
+            if shown.elapsed() < NOTICE_TIME {
+                d.draw_rectangle(500, 112, 440, 26, Color::WHITE);
+                d.draw_text(text, 500, 115, 20, *color);
+            }
+        }
+
+        // Nothing is shown while the position on the board is still being probed
+        if let Some((_, Some(probe))) = self.tablebase_probe.filter(|&(hash, _)| hash == self.position.hash) {
+            d.draw_text(&probe.to_string(), 500, 146, 20, Color::DARKBLUE);
+        } else if let Some(opening) = self.openings.opening(&self.positions[..=self.ply]) {
+            d.draw_text(&opening.to_string(), 500, 146, 20, Color::DARKBLUE);
+        }
+    }
+
+    // Minutes and seconds, with tenths once there are less than ten seconds left
+    fn format_clock(time: Duration) -> String {
+        let seconds = time.as_secs();
+        if seconds < 10 {
+            format!("{}.{}", seconds, time.subsec_millis() / 100)
+        } else {
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+    }
+
+    fn draw_clocks(&self, d: &mut RaylibDrawHandle) {
+        let clock = match &self.clock {
+            Some(clock) => clock,
+            None => return,
+        };
+        let now = Instant::now();
+
+        for (owner, rect) in [(Owner::Black, BLACK_CLOCK_RECT), (Owner::White, WHITE_CLOCK_RECT)] {
+            let remaining = clock.remaining(owner, now);
+            let (background, foreground) = if remaining.is_zero() {
+                (Color::RED, Color::WHITE)
+            } else if clock.running() == Some(owner) {
+                (Color::DARKGRAY, Color::WHITE)
+            } else {
+                (Color::LIGHTGRAY, Color::BLACK)
+            };
+            d.draw_rectangle_rec(rect, background);
+
+            let text = Game::format_clock(remaining);
+            let text_width = d.measure_text(&text, 30);
+            d.draw_text(&text, (rect.x + rect.width) as i32 - text_width - 12, rect.y as i32 + 5, 30, foreground);
+        }
+    }
+
+    // Run the clock of the side to move, and end the game when a flag falls
+    fn update_clock(&mut self) {
+        let now = Instant::now();
+        // Over the network the clocks wait for both players, and stop if one goes
+        let stopped = self.game_over() || self.network.as_ref().is_some_and(|network| !network.is_ready());
+        let clock = match &mut self.clock {
+            Some(clock) => clock,
+            None => return,
+        };
+
+        if stopped {
+            clock.pause(now);
+            return;
+        }
+        clock.start(self.position.turn, now);
+
+        if let Some(flagged) = clock.flagged(now) {
+            clock.pause(now);
+            // Only the final position counts, the line can't be browsed mid-game
+            self.declare_result(self.position.timeout(flagged));
+        }
+    }
+
+    // End the game for a reason the position itself doesn't show
+    fn declare_result(&mut self, result: GameResult) {
+        self.declared_result = Some(result);
+        self.result = Some(result);
+        self.thinking = None;
+    }
+
+    // Whether the game at the end of the line has finished
+    fn game_over(&self) -> bool {
+        let (last, before) = self.positions.split_last().unwrap();
+        self.declared_result.is_some() || last.outcome(before).is_some()
+    }
+
+    // Timed and network games are played for real, one move after the other
+    fn is_live(&self) -> bool {
+        self.clock.is_some() || self.network.is_some()
+    }
+
+    // A live game has to be played out before moves can be taken back or looked through
+    fn can_browse(&self) -> bool {
+        !self.is_live() || self.game_over()
+    }
+
+    pub fn set_time_control(&mut self, control: Option<TimeControl>) {
+        self.clock = control.map(Clock::new);
+    }
+
+    fn move_list_area(&self) -> Rectangle {
+        if self.analysis.is_some() { ANALYSIS_MOVE_LIST_RECT } else { MOVE_LIST_RECT }
+    }
+
+    fn move_list_visible_rows(&self) -> usize {
+        (self.move_list_area().height / MOVE_ROW_HEIGHT) as usize
+    }
+
+    // Row and column (0 for white, 1 for black) of a move in the list. A game
+    // starting with black to move leaves the first white column empty.
+    fn move_list_cell(&self, index: usize) -> (usize, usize) {
+        let start_offset = match self.positions[0].turn {
+            Owner::White => 0,
+            Owner::Black => 1,
+        };
+        ((index + start_offset) / 2, (index + start_offset) % 2)
+    }
+
+    fn move_list_rect(&self, index: usize) -> Option<Rectangle> {
+        let (row, column) = self.move_list_cell(index);
+        if row < self.move_list_scroll || row >= self.move_list_scroll + self.move_list_visible_rows() {
+            return None;
+        }
+
+        let area = self.move_list_area();
+        Some(Rectangle {
+            x: area.x + 60.0 + column as f32 * 120.0,
+            y: area.y + (row - self.move_list_scroll) as f32 * MOVE_ROW_HEIGHT,
+            width: 115.0,
+            height: MOVE_ROW_HEIGHT,
+        })
+    }
+
+    fn draw_move_list(&self, d: &mut RaylibDrawHandle) {
+        let area = self.move_list_area();
+        d.draw_rectangle_lines_ex(area, 1.0, Color::LIGHTGRAY);
+
+        for (index, san) in self.line_san.iter().enumerate() {
+            let rect = match self.move_list_rect(index) {
+                Some(rect) => rect,
+                None => continue,
+            };
+
+            let (row, column) = self.move_list_cell(index);
+            if column == 0 || index == 0 {
+                let number = self.positions[0].fullmove_number as usize + row;
+                d.draw_text(&format!("{}.", number), area.x as i32 + 8, rect.y as i32 + 2, 20, Color::DARKGRAY);
+            }
+
+            // The move that led to the position on the board
+            if index + 1 == self.ply {
+                d.draw_rectangle_rec(rect, Color::SKYBLUE);
+            }
+            // Taken back moves are greyed out until they are redone or replaced
+            let color = if index < self.ply { Color::BLACK } else { Color::GRAY };
+            d.draw_text(san, rect.x as i32 + 4, rect.y as i32 + 2, 20, color);
+        }
+    }
+
+    // Clicking a move jumps to the position after it, the wheel scrolls long games
+    fn handle_move_list(&mut self, d: &mut RaylibDrawHandle) {
+        let mouse = d.get_mouse_position();
+        if !self.move_list_area().check_collision_point_rec(mouse) {
+            return;
+        }
+
+        let wheel = d.get_mouse_wheel_move();
+        if wheel > 0.0 {
+            self.move_list_scroll = self.move_list_scroll.saturating_sub(1);
+        } else if wheel < 0.0 && self.move_list_scroll + self.move_list_visible_rows() < self.move_list_rows() {
+            self.move_list_scroll += 1;
+        }
+
+        if d.is_mouse_button_released(MOUSE_BUTTON_LEFT) && self.can_browse() {
+            let clicked = (0..self.line.len())
+                .find(|&index| self.move_list_rect(index).is_some_and(|rect| rect.check_collision_point_rec(mouse)));
+            if let Some(index) = clicked {
+                self.go_to_ply(index + 1);
+            }
+        }
+    }
+
+    fn move_list_rows(&self) -> usize {
+        match self.line.len() {
+            0 => 0,
+            len => self.move_list_cell(len - 1).0 + 1,
+        }
+    }
+
+    fn draw_game_over(&self, d: &mut RaylibDrawHandle, result: GameResult) {
+        d.draw_rectangle(0, 0, 480, 480, Color {r: 0, g: 0, b: 0, a: 120 });
+        let rect = Rectangle { x: 40.0, y: 170.0, width: 400.0, height: 170.0 };
+        Panel::new(rect, Color::RAYWHITE, &result.to_string(), 24).draw(d);
+        self.new_game_button.draw(d);
+    }
+
+    // Show the position after the first `ply` moves of the line
+    fn go_to_ply(&mut self, ply: usize) {
+        self.ply = ply.min(self.line.len());
+        self.position = self.positions[self.ply];
+        self.result = self.position.outcome(&self.positions[..self.ply]);
+        if self.ply == self.line.len() {
+            self.result = self.result.or(self.declared_result);
+        }
+        self.pending_promotion = None;
+        self.selected = None;
+        self.thinking = None;
+        self.dragging = false;
+        self.dragging_piece = None;
+
+        // Keep the current move in view
+        if self.ply > 0 {
+            let row = self.move_list_cell(self.ply - 1).0;
+            if row < self.move_list_scroll {
+                self.move_list_scroll = row;
+            } else if row >= self.move_list_scroll + self.move_list_visible_rows() {
+                self.move_list_scroll = row + 1 - self.move_list_visible_rows();
+            }
+        } else {
+            self.move_list_scroll = 0;
+        }
+    }
+
+    // Play a move made on the board or typed in, asking which piece to promote to first
+    fn try_move(&mut self, mv: Move) {
+        if self.position.is_legal(mv) {
+            self.play_move(mv);
+        } else if mv.promotion.is_none() && self.position.is_legal(Move::with_promotion(mv.from, mv.to, PieceType::Queen)) {
+            self.pending_promotion = Some(mv);
+        }
+    }
+
+    // Either UCI like "g1f3" or SAN like "Nf3"
+    fn parse_move_input(&self, text: &str) -> Option<Move> {
+        self.position.parse_uci(&text.to_ascii_lowercase()).or_else(|| self.position.parse_san(text).ok())
+    }
+
+    fn play_move(&mut self, mv: Move) {
+        let mover = self.position.turn;
+        let remote_moved = self.is_remote(mover);
+        let now = Instant::now();
+        if let Some(clock) = &mut self.clock {
+            clock.press(now);
+        }
 
-        if self.dragging_piece.is_some() {
-            match self.dragging_piece.unwrap().piece_type {
-               PieceType::Bishop => {
-                self.dragging_piece.expect("WHY ISN'T THERE A PIECE HERE").draw_diagonal(&self, d, (self.mouse_coords.0 as i8, self.mouse_coords.1 as i8));
-               } 
-               _ => {
-                self.dragging_piece.expect("WHY ISN'T THERE A PIECE HERE").draw_straight(&self, d, (self.mouse_coords.0 as i8, self.mouse_coords.1 as i8));
-               }
+        if let Some(network) = &mut self.network {
+            if network.is_connected() {
+                network.status = None;
+            }
+            if remote_moved {
+                // Any offer of ours lapses once they move
+                network.draw_sent = false;
+            } else {
+                let time_left = self.clock.map(|clock| clock.remaining(mover, now));
+                network.send(&Message::Move { mv, time_left });
+                // Moving instead of answering turns their offer down
+                if network.draw_offered {
+                    network.draw_offered = false;
+                    network.send(&Message::DrawDecline);
+                }
+            }
+        }
+
+        // Playing anything but the next move of the line replaces the rest of it
+        if self.line.get(self.ply) != Some(&mv) {
+            self.line.truncate(self.ply);
+            self.line_san.truncate(self.ply);
+            self.positions.truncate(self.ply + 1);
+
+            let mut next = self.position;
+            next.apply_move(mv);
+            self.line.push(mv);
+            self.line_san.push(self.position.to_san(mv));
+            self.positions.push(next);
+        }
+        self.go_to_ply(self.ply + 1);
+    }
+
+    fn take_back(&mut self) {
+        if self.ply == 0 {
+            return;
+        }
+        // Against the computer, take back its reply along with your own move
+        let mut ply = self.ply - 1;
+        if ply > 0 && self.is_computer(self.positions[ply].turn) {
+            ply -= 1;
+        }
+        self.go_to_ply(ply);
+    }
+
+    fn is_computer(&self, owner: Owner) -> bool {
+        matches!(self.opponent, Opponent::Computer { colour, .. } if colour == owner)
+    }
+
+    fn is_remote(&self, owner: Owner) -> bool {
+        self.opponent == Opponent::Remote { colour: owner }
+    }
+
+    pub fn set_opponent(&mut self, opponent: Opponent) {
+        self.opponent = opponent;
+        // Your own pieces go at the bottom
+        if let Opponent::Computer { colour, .. } | Opponent::Remote { colour } = opponent {
+            self.flipped = colour == Owner::White;
+        }
+        if let Opponent::Computer { colour, difficulty } = opponent {
+
+            let tag = match colour {
+                Owner::White => "White",
+                Owner::Black => "Black",
+            };
+            let name = format!("Rusty Chess ({})", difficulty.name());
+            if let Some(player) = self.tags.iter_mut().find(|(key, _)| key == tag) {
+                player.1 = name;
             }
         }
+    }
+
+    // Start the computer thinking when it is its move, and play the move once it has one.
+    // It only plays at the end of the line, so earlier positions can still be looked through.
+    fn update_computer(&mut self) {
+        if let Some(thinking) = &self.thinking {
+            if let Some(info) = thinking.try_result() {
+                self.thinking = None;
+                if let Some(mv) = info.best_move() {
+                    self.play_move(mv);
+                }
+            }
+            return;
+        }
+
+        if let Opponent::Computer { colour, difficulty } = self.opponent {
+            if colour == self.position.turn && self.result.is_none() && self.ply == self.line.len() {
+                // Book moves are played straight away, for as long as the game stays in the book
+                let random = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos() as u64);
+                if let Some(mv) = self.book.as_ref().and_then(|book| book.choose(&self.position, random)) {
+                    self.play_move(mv);
+                    return;
+                }
+
+                let mut limits = difficulty.limits();
+                // On the clock it also has to budget its time like anyone else
+                if let Some(clock) = &self.clock {
+                    let budget = think_time(clock.remaining(colour, Instant::now()), clock.control().bonus(), DEFAULT_MOVES_TO_GO);
+                    limits.movetime = Some(limits.movetime.map_or(budget, |movetime| movetime.min(budget)));
+                }
+                let history = self.positions[..self.ply].to_vec();
+                // Any tablebases are looked in on the search's thread before it searches
+                let (table, tablebases) = (Arc::clone(&self.table), self.tablebases.clone());
+                self.thinking = Some(SearchThread::spawn(self.position, history, limits, table, tablebases, |_| {}));
+            }
+        }
+    }
+
+    // Probing reads tables from disk, so it is done on another thread and only
+    // when the position changes
+    fn update_tablebase_probe(&mut self) {
+        let tablebases = match &self.tablebases {
+            Some(tablebases) => tablebases,
+            None => return,
+        };
+        if let Some((hash, probe)) = self.probing.as_ref().and_then(|(hash, thread)| thread.try_result().map(|probe| (*hash, probe))) {
+            self.tablebase_probe = Some((hash, probe));
+            self.probing = None;
+        }
+
+        let hash = self.position.hash;
+        let shown = self.tablebase_probe.is_some_and(|(probed, _)| probed == hash);
+        let under_way = self.probing.as_ref().is_some_and(|(probing, _)| *probing == hash);
+        if !shown && !under_way {
+            self.probing = Some((hash, ProbeThread::spawn(Arc::clone(tablebases), self.position)));
+        }
+    }
+
+    // Both sides are moved by hand while the engine keeps looking at the position
+    pub fn start_analysis(&mut self) {
+        self.set_opponent(Opponent::Human);
+        self.clock = None;
+        self.analysis = Some(Analysis::new());
+    }
 
-        self.draw_pieces(d, textures);
+    // Keep the engine on the position shown, whichever move of the line that is
+    fn update_analysis(&mut self) {
+        if let Some(analysis) = &mut self.analysis {
+            analysis.update(&self.position, &self.positions[..self.ply], &self.table);
+        }
+    }
+
+    fn draw_analysis(&self, d: &mut RaylibDrawHandle) {
+        let analysis = match &self.analysis {
+            Some(analysis) => analysis,
+            None => return,
+        };
+        analysis.draw_eval_bar(d, EVAL_BAR_RECT, self.flipped);
+        analysis.draw_lines(d, ANALYSIS_LINES_RECT);
+        // Where the clocks would be
+        if let Some(depth) = analysis.depth() {
+            d.draw_text(&format!("Depth {}", depth), 760, 25, 20, Color::DARKGRAY);
+        }
+    }
+
+    // The engine's best move as an arrow over the pieces
+    fn draw_best_move_arrow(&self, d: &mut RaylibDrawHandle) {
+        let mv = match self.analysis.as_ref().and_then(Analysis::best_move) {
+            Some(mv) => mv,
+            None => return,
+        };
+        let from = self.space_center(mv.from);
+        let to = self.space_center(mv.to);
+        let length = ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt();
+        let (dx, dy) = ((to.x - from.x) / length, (to.y - from.y) / length);
+
+        let head = SPACE_SIZE * 0.4;
+        let base = Vector2 { x: to.x - dx * head, y: to.y - dy * head };
+        let (nx, ny) = (-dy * head * 0.6, dx * head * 0.6);
+        d.draw_line_ex(from, base, SPACE_SIZE * 0.15, ARROW_COLOR);
+        // raylib wants the corners counter-clockwise as they appear on screen
+        d.draw_triangle(to, Vector2 { x: base.x - nx, y: base.y - ny }, Vector2 { x: base.x + nx, y: base.y + ny }, ARROW_COLOR);
+    }
+
+    // Act on whatever the other player has sent since the last frame
+    fn update_network(&mut self) {
+        let messages: Vec<Message> = match &mut self.network {
+            Some(network) => std::iter::from_fn(|| network.poll()).collect(),
+            None => return,
+        };
+
+        for message in messages {
+            if let Err(e) = self.handle_message(message) {
+                if let Some(network) = &mut self.network {
+                    network.close(format!("Disconnected, {}", e));
+                }
+                return;
+            }
+        }
+    }
+
+    fn handle_message(&mut self, message: Message) -> Result<(), ProtocolError> {
+        let started = self.network.as_ref().is_some_and(|network| network.started);
+
+        // Only the guest is sent a new game, once, and the rest has to wait for it
+        if let Message::NewGame { guest_colour, time_control, start } = message {
+            if started {
+                return Err(ProtocolError::Malformed(message.to_string()));
+            }
+            self.positions = vec![start];
+            self.line.clear();
+            self.line_san.clear();
+            self.go_to_ply(0);
+            self.set_time_control(time_control);
+            self.set_opponent(Opponent::Remote { colour: guest_colour.opponent() });
+            if let Some(network) = &mut self.network {
+                network.started = true;
+                network.status = None;
+            }
+            return Ok(());
+        }
+
+        let remote = match self.opponent {
+            Opponent::Remote { colour } if started => colour,
+            _ => return Err(ProtocolError::NotStarted),
+        };
+        // Whatever crossed the game ending on the way doesn't count
+        if self.game_over() {
+            return Ok(());
+        }
+
+        match message {
+            Message::Move { mv, time_left } => {
+                if self.position.turn != remote {
+                    return Err(ProtocolError::OutOfTurn);
+                }
+                self.play_move(check_move(&self.position, mv)?);
+                // Their own clock decides how long they took
+                if let (Some(clock), Some(time_left)) = (&mut self.clock, time_left) {
+                    clock.set_remaining(remote, time_left);
+                }
+            }
+            Message::Resign => self.declare_result(GameResult::Resignation { winner: remote.opponent() }),
+            Message::DrawOffer => {
+                if let Some(network) = &mut self.network {
+                    network.draw_offered = true;
+                    network.status = Some("Your opponent offers a draw".to_string());
+                }
+            }
+            Message::DrawAccept => {
+                // An offer of ours that lapsed as it was accepted stays lapsed
+                if self.network.as_ref().is_some_and(|network| network.draw_sent) {
+                    self.declare_result(GameResult::DrawAgreed);
+                }
+            }
+            Message::DrawDecline => {
+                if let Some(network) = &mut self.network {
+                    network.draw_sent = false;
+                    network.status = Some("Draw declined".to_string());
+                }
+            }
+            Message::Hello { .. } | Message::NewGame { .. } => {}
+        }
         Ok(())
     }
 
+    // Resign or offer a draw in a network game, or leave one that can't go on
+    fn handle_network_buttons(&mut self, d: &mut RaylibDrawHandle) {
+        let game_over = self.game_over();
+        let network = match &mut self.network {
+            Some(network) if !game_over => network,
+            _ => return,
+        };
 
-    pub fn default() -> Game {
-        let mut layout: [[Space; 8]; 8] = [[Space::default(); 8]; 8];
-        let piece_order = [2, 3, 4, 5, 6, 4, 3, 2];
-
-        for row in 0..layout.len() {
-            for col in 0..layout[row].len() {
-                let space_rect: Rectangle = Rectangle {
-                                x: col as f32 * 60.0,
-                                y: row as f32 * 60.0,
-                                width: 60.0,
-                                height: 60.0
-                            };
-
-                let owner = if row < 2 { 0 } else { 1 };
-                let piece_type = if row < 1 || row == 7 { piece_order[col] } else { 1 }; 
-
-                let mut space: Space = Space {
-                    rect: space_rect,
-                    piece: None,
-                    is_occupied: false,
-                };
-
-                if !(2..6).contains(&row) {
-                    let piece: Piece = Piece::new(space, owner, piece_type);
-                    space.piece = Some(piece);
+        if !network.is_ready() {
+            self.resign_button.text = "Leave".to_string();
+            let leave = self.focus.update(d, &mut self.resign_button);
+            self.resign_button.draw(d);
+            if leave {
+                *self = Game::default();
+            }
+            return;
+        }
+
+        let remote = match self.opponent {
+            Opponent::Remote { colour } => colour,
+            _ => return,
+        };
+        self.resign_button.text = "Resign".to_string();
+        let resign = self.focus.update(d, &mut self.resign_button);
+        self.draw_button.text = if network.draw_offered { "Accept" } else { "Draw" }.to_string();
+        self.draw_button.selected = network.draw_sent;
+        let draw = self.focus.update(d, &mut self.draw_button);
+        self.resign_button.draw(d);
+        self.draw_button.draw(d);
+
+        if resign {
+            network.send(&Message::Resign);
+            self.declare_result(GameResult::Resignation { winner: remote });
+            return;
+        }
+        if draw {
+            if network.draw_offered {
+                network.send(&Message::DrawAccept);
+                self.declare_result(GameResult::DrawAgreed);
+            } else if !network.draw_sent {
+                network.send(&Message::DrawOffer);
+                network.draw_sent = true;
+                network.status = Some("Draw offered".to_string());
+            }
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.ply < self.line.len() {
+            self.go_to_ply(self.ply + 1);
+        }
+    }
+
+    // Left/Right (or Ctrl+Z/Ctrl+Y) take back and redo moves, Home/End jump to either end,
+    // in a timed game only once it is over.
+    // Ctrl+C copies the position as FEN, Ctrl+V sets up the FEN on the clipboard
+    // (except in a network game, where both sides have to have the same one),
+    // Ctrl+S saves the game as PGN. F flips the board.
+    fn handle_shortcuts(&mut self, d: &mut RaylibDrawHandle) {
+        // Keys typed into the move box are meant for it
+        if self.move_input.focused() {
+            return;
+        }
+
+        if d.is_key_pressed(KEY_F) {
+            self.flipped = !self.flipped;
+        }
+
+        let can_browse = self.can_browse();
+        if can_browse {
+            if d.is_key_pressed(KEY_LEFT) {
+                self.take_back();
+            } else if d.is_key_pressed(KEY_RIGHT) {
+                self.redo();
+            } else if d.is_key_pressed(KEY_HOME) {
+                self.go_to_ply(0);
+            } else if d.is_key_pressed(KEY_END) {
+                self.go_to_ply(self.line.len());
+            }
+        }
+
+        if !d.is_key_down(KEY_LEFT_CONTROL) && !d.is_key_down(KEY_RIGHT_CONTROL) {
+            return;
+        }
+
+        if d.is_key_pressed(KEY_Z) && can_browse {
+            self.take_back();
+        } else if d.is_key_pressed(KEY_Y) && can_browse {
+            self.redo();
+        } else if d.is_key_pressed(KEY_S) {
+            match self.save_pgn() {
+                Ok(path) => self.show_notice(format!("Saved to {}", path.display()), Color::DARKGREEN),
+                Err(e) => self.show_error(format!("Could not save game: {}", e)),
+            }
+        } else if d.is_key_pressed(KEY_C) {
+            match d.set_clipboard_text(&self.to_fen()) {
+                Ok(()) => self.show_notice("FEN copied".to_string(), Color::DARKGREEN),
+                Err(e) => self.show_error(format!("Could not copy FEN: {}", e)),
+            }
+        } else if d.is_key_pressed(KEY_V) && self.network.is_none() {
+            let text = d.get_clipboard_text().unwrap_or_default();
+            match Game::from_fen(text.trim()) {
+                Ok(game) => {
+                    // The new position starts with full clocks
+                    let clock = self.clock.map(|clock| Clock::new(clock.control()));
+                    *self = Game { state: self.state, opponent: self.opponent, clock, flipped: self.flipped, book: self.book.clone(), tablebases: self.tablebases.clone(), analysis: self.analysis.take().map(|_| Analysis::new()), ..game };
+                }
+                Err(e) => self.show_error(format!("Could not load FEN: {}", e)),
+            }
+        }
+    }
+
+    fn show_notice(&mut self, text: String, color: Color) {
+        self.notice = Some((text, color, Instant::now()));
+    }
+
+    // Errors also go to stderr, where they can be read in full
+    fn show_error(&mut self, text: String) {
+        eprintln!("{}", text);
+        self.show_notice(text, Color::RED);
+    }
+
+    pub fn run(&mut self, d: &mut RaylibDrawHandle, _thread: RaylibThread, pieces: &PieceSet) -> Result<(), Error> {
+        d.clear_background(Color::WHITE);
+        self.draw_board(d);
+        self.focus.begin(d);
+        let move_typed = self.focus.update(d, &mut self.move_input);
+        self.handle_shortcuts(d);
+        self.handle_move_list(d);
+        self.update_network();
+        self.update_clock();
+        self.update_computer();
+        self.update_tablebase_probe();
+        self.update_analysis();
+        self.draw_status(d);
+        self.draw_clocks(d);
+        self.handle_network_buttons(d);
+        self.draw_analysis(d);
+        self.draw_move_list(d);
+        let rejected = self.rejected_input.as_ref().filter(|&text| *text == self.move_input.text);
+        self.move_input.error = rejected.map(|_| "Not a legal move");
+        self.move_input.draw(d);
+
+        if let Some(result) = self.result {
+            self.draw_pieces(d, pieces);
+            let new_game = self.focus.update(d, &mut self.new_game_button);
+            self.draw_game_over(d, result);
+            if new_game {
+                // A fresh game starts out on the main menu
+                *self = Game::default();
+            }
+            return Ok(());
+        }
+
+        if let Some(mv) = self.pending_promotion {
+            if d.is_mouse_button_pressed(MOUSE_BUTTON_RIGHT) {
+                self.pending_promotion = None;
+            } else if d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+                // Clicking anywhere but one of the choices snaps the pawn back
+                let mouse = d.get_mouse_position();
+                self.pending_promotion = None;
+                for (piece_type, rect) in self.promotion_rects(mv) {
+                    if rect.check_collision_point_rec(mouse) {
+                        self.play_move(Move::with_promotion(mv.from, mv.to, piece_type));
+                    }
+                }
+            }
+
+            self.draw_pieces(d, pieces);
+            if self.pending_promotion.is_some() {
+                self.draw_promotion_picker(d, pieces, mv);
+            }
+            return Ok(());
+        }
+
+        // Finished live games can be looked through, but not played on from earlier moves
+        let browsing_live_game = self.is_live() && self.ply < self.line.len();
+        let waiting = self.network.as_ref().is_some_and(|network| !network.is_ready());
+        let their_move = self.is_computer(self.position.turn) || self.is_remote(self.position.turn);
+        if their_move || browsing_live_game || waiting {
+            self.draw_pieces(d, pieces);
+            return Ok(());
+        }
+
+        // Keyboard entry, for anyone who'd rather type "e4" or "g1f3" than use the mouse
+        if move_typed {
+            let text = self.move_input.text.clone();
+            match self.parse_move_input(&text) {
+                Some(mv) => {
+                    self.move_input.text.clear();
+                    self.try_move(mv);
                 }
+                None => self.rejected_input = Some(text),
+            }
+        }
 
-                layout[col][row] = space;
-                println!("{:?}", layout[row][col].piece)
+        let mouse = d.get_mouse_position();
+        if d.is_mouse_button_pressed(MOUSE_BUTTON_RIGHT) {
+            // Right click puts down whatever piece is picked up
+            self.selected = None;
+            self.dragging = false;
+            self.dragging_piece = None;
+        } else if d.is_mouse_button_pressed(MOUSE_BUTTON_LEFT) {
+            let own_piece = self.get_piece_at(mouse).filter(|piece| piece.owner == self.position.turn);
+            match (own_piece, self.square_at(mouse)) {
+                // Pick up one of your pieces, to drag it or to click where it goes
+                (Some(piece), Some(square)) => {
+                    let space_rect = self.space_rect(square);
+                    self.selected = Some(square);
+                    self.mouse_square = square;
+                    self.dragging = true;
+                    self.dragging_piece = Some(piece);
+                    self.offset.x = mouse.x - space_rect.x;
+                    self.offset.y = mouse.y - space_rect.y;
+                }
+                // With a piece picked up, clicking another space moves it there
+                (None, Some(target)) => {
+                    if let Some(from) = self.selected.take() {
+                        self.try_move(Move::new(from, target));
+                    }
+                }
+                _ => self.selected = None,
+            }
+        } else if d.is_mouse_button_released(MOUSE_BUTTON_LEFT) && self.dragging {
+            self.dragging = false;
+            self.dragging_piece = None;
+            // Dropping the piece back on its own space keeps it picked up for a click move.
+            // Illegal drops are left alone, which snaps the piece back to its space.
+            if let Some(target) = self.square_at(mouse).filter(|&target| target != self.mouse_square) {
+                self.selected = None;
+                self.try_move(Move::new(self.mouse_square, target));
             }
         }
 
+        if self.dragging {
+            self.drag_position.x = mouse.x - self.offset.x;
+            self.drag_position.y = mouse.y - self.offset.y;
+        }
+
+        if let Some(from) = self.selected {
+            d.draw_rectangle_rec(self.space_rect(from), MOVE_HINT_COLOR);
+            self.draw_valid_moves(d, from);
+        }
+
+        self.draw_pieces(d, pieces);
+        self.draw_best_move_arrow(d);
+        Ok(())
+    }
+
+
+    // A game is started afresh for every reset, so what takes a while to set up
+    // is only set up the once and then shared
+    pub fn default() -> Game {
         Game {
-            turn: true,
-            layout,
-            state: 0,
+            position: Position::default(),
+            state: GameState::Menu,
+            result: None,
+            tags: Pgn::new("?", "?").tags,
+            opponent: Opponent::Human,
+            clock: None,
+            declared_result: None,
+            network: None,
+            thinking: None,
+            table: engine_table(),
+            book: None,
+            tablebases: None,
+            tablebase_probe: None,
+            probing: None,
+            analysis: None,
+            openings: openings(),
+            positions: vec![Position::default()],
+            line: Vec::new(),
+            line_san: Vec::new(),
+            ply: 0,
+            move_list_scroll: 0,
             offset: Vector2::default(),
             dragging_piece: None,
             dragging: false,
-            mouse_coords: (0, 0)
-        }
+            drag_position: Vector2::default(),
+            mouse_square: Square::new(0, 0),
+            flipped: false,
+            selected: None,
+            pending_promotion: None,
+            move_input: TextBox::new(MOVE_INPUT_RECT, "Tab to type a move, e.g. e4 or g1f3", 8),
+            rejected_input: None,
+            notice: None,
+            focus: Focus::new(),
+            resign_button: Button::new(RESIGN_RECT, "Resign"),
+            draw_button: Button::new(DRAW_RECT, "Draw"),
+            new_game_button: Button::large(NEW_GAME_RECT, "New game"),
         }
     }
+
+    // Start from an arbitrary position instead of the usual setup
+    pub fn from_position(position: Position) -> Game {
+        let mut game = Game::default();
+        game.positions = vec![position];
+        game.go_to_ply(0);
+        game
+    }
+
+    // A FEN that reads fine but can't be played from, like one without a
+    // king, is refused as well
+    pub fn from_fen(fen: &str) -> Result<Game, String> {
+        let position = Position::from_fen(fen).map_err(|e| e.to_string())?;
+        position.validate().map_err(|e| e.to_string())?;
+        Ok(Game::from_position(position))
+    }
+
+    pub fn to_fen(&self) -> String {
+        self.position.to_fen()
+    }
+
+    // The whole line is exported, including moves that were taken back
+    pub fn to_pgn(&self) -> Pgn {
+        let (last, before) = self.positions.split_last().unwrap();
+        let mut pgn = Pgn {
+            tags: self.tags.clone(),
+            start: self.positions[0],
+            moves: self.line.clone(),
+        };
+        let result = last.outcome(before).or(self.declared_result);
+        pgn.set_tag("Result", result.map_or("*", GameResult::to_pgn));
+        pgn
+    }
+
+    // Set up a recorded game at its start, to be replayed move by move
+    pub fn from_pgn(pgn: &Pgn) -> Game {
+        let mut game = Game::default();
+        game.tags = pgn.tags.clone();
+        game.positions = pgn.positions();
+        game.line = pgn.moves.clone();
+        game.line_san = game.positions.iter().zip(&game.line).map(|(p, &mv)| p.to_san(mv)).collect();
+        game.go_to_ply(0);
+        game
+    }
+
+    // Writes the game to saved_games/ in the working directory
+    pub fn save_pgn(&self) -> io::Result<PathBuf> {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let path = PathBuf::from("saved_games").join(format!("game_{}.pgn", seconds));
+
+        fs::create_dir_all("saved_games")?;
+        fs::write(&path, self.to_pgn().to_string())?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,99 @@
+use rusty_chess::net::{Event, Host, Message, Peer, DEFAULT_PORT};
+use std::io;
+
+enum Connection {
+    // Hosting, with the new game to send whoever joins
+    Waiting(Host, Message),
+    Connected(Peer),
+    Closed,
+}
+
+// The link to a player on another machine, as the game sees it. The protocol
+// itself is in the library's net module.
+pub struct Network {
+    connection: Connection,
+    // Whether the game has been set up on both ends. The host sets it up as
+    // soon as someone joins, the guest once the host's new game arrives.
+    pub started: bool,
+    // What is going on with the connection, shown beside the board
+    pub status: Option<String>,
+    // A draw offer from the other side waiting for an answer
+    pub draw_offered: bool,
+    // Our own draw offer, until the other side answers or moves
+    pub draw_sent: bool,
+}
+
+impl Network {
+    // Listen on every interface, on the port of `address` if it has one
+    pub fn host(address: &str, new_game: Message) -> io::Result<Network> {
+        let port = address.rsplit(':').next().and_then(|port| port.parse().ok()).unwrap_or(DEFAULT_PORT);
+        let host = Host::listen(&format!("0.0.0.0:{}", port))?;
+        let status = format!("Waiting for an opponent on port {}", host.port()?);
+        Ok(Network::new(Connection::Waiting(host, new_game), status))
+    }
+
+    pub fn join(address: &str) -> io::Result<Network> {
+        let peer = Peer::connect(address)?;
+        Ok(Network::new(Connection::Connected(peer), "Waiting for the host to start".to_string()))
+    }
+
+    fn new(connection: Connection, status: String) -> Network {
+        Network { connection, started: false, status: Some(status), draw_offered: false, draw_sent: false }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        matches!(self.connection, Connection::Connected(_))
+    }
+
+    // Connected, with the game set up on both ends
+    pub fn is_ready(&self) -> bool {
+        self.is_connected() && self.started
+    }
+
+    pub fn close(&mut self, reason: String) {
+        self.connection = Connection::Closed;
+        self.status = Some(reason);
+    }
+
+    pub fn send(&mut self, message: &Message) {
+        if let Connection::Connected(peer) = &mut self.connection {
+            if let Err(e) = peer.send(message) {
+                self.close(format!("Connection lost: {}", e));
+            }
+        }
+    }
+
+    // The next message from the other side, if there is one. Someone joining
+    // and the connection failing are dealt with in here.
+    pub fn poll(&mut self) -> Option<Message> {
+        match &mut self.connection {
+            Connection::Waiting(host, new_game) => {
+                match host.accept() {
+                    Ok(Some(mut peer)) => match peer.send(new_game) {
+                        Ok(()) => {
+                            self.connection = Connection::Connected(peer);
+                            self.started = true;
+                            self.status = None;
+                        }
+                        Err(e) => self.close(format!("Connection lost: {}", e)),
+                    },
+                    Ok(None) => {}
+                    Err(e) => self.close(format!("Could not accept a connection: {}", e)),
+                }
+                None
+            }
+            Connection::Connected(peer) => match peer.poll()? {
+                Event::Message(message) => Some(message),
+                Event::Error(e) => {
+                    self.close(format!("Disconnected, {}", e));
+                    None
+                }
+                Event::Closed => {
+                    self.close("Your opponent left".to_string());
+                    None
+                }
+            },
+            Connection::Closed => None,
+        }
+    }
+}
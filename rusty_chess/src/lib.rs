@@ -0,0 +1,10 @@
+// Chess rules core and engine for Rusty Chess.
+//
+// Nothing in here depends on raylib, so the rules can be used from tests,
+// servers and command line tools without opening a window. The game binary
+// in `main.rs` renders on top of it.
+
+pub mod board;
+pub mod engine;
+pub mod clock;
+pub mod net;
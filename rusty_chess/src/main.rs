@@ -1,90 +1,268 @@
-use crate::components::menu;
-use crate::board::piece::{Piece, PieceType};
+use crate::components::{editor, menu, settings};
+use crate::components::viewport::Viewport;
+use crate::components::editor::EditorAction;
+use crate::components::menu::NetworkRole;
+use crate::game::{GameState, Opponent, SPACE_SIZE};
+use crate::lan_session::Network;
+use crate::theme::{PieceSet, DEFAULT_THEME, DEFAULT_THEME_DIR};
+use rusty_chess::board::fen::STARTING_FEN;
+use rusty_chess::board::pgn::Pgn;
+use rusty_chess::board::position::Position;
+use rusty_chess::engine::book::Book;
+use rusty_chess::engine::syzygy::Tablebases;
+use rusty_chess::engine::uci;
+use rusty_chess::net::Message;
 
 use std::fmt::Error;
-use glob::glob;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::{fs, io};
+use std::time::Instant;
 use raylib::prelude::*;
 
 const WIDTH: i32 = 960;
 const HEIGHT: i32 = 540;
 
+mod analysis;
 mod game;
-mod board;
 mod components;
+mod lan_session;
+mod theme;
 
-fn piece_index(str: &String) -> (Result<usize, Error>, u8) {
-    if str.contains("white") {
-        (Ok(str.find("white").unwrap()), 1)
-    } else {
-        (Ok(str.find("black").unwrap()), 2)
-    }
-}
-fn string_to_piece_name(s: &str) -> Result<PieceType, Error> {
-    match s {
-        "pawn" | "Pawn" => Ok(PieceType::Pawn),
-        "rook" | "Rook" => Ok(PieceType::Rook),
-        "bishop" | "Bishop" => Ok(PieceType::Bishop),
-        "knight" | "Knight" => Ok(PieceType::Knight),
-        "king" | "King" => Ok(PieceType::King),
-        "queen" | "Queen" => Ok(PieceType::Queen),
-        _ => panic!("Piece not found! Zoinks!"),
-    }
-}
-fn load_pieces_textures(
-        rl: &mut RaylibHandle,
-        thread: RaylibThread,
-    ) -> Result<HashMap<PieceType,Vec<Texture2D>>, Error> {
+// `perft [divide] <depth> [FEN]` counts the move paths from a position, by
+// default the starting one. `divide` breaks the count down by first move.
+fn run_perft(args: &[String]) {
+    let divide = args.first().map(String::as_str) == Some("divide");
+    let args = if divide { &args[1..] } else { args };
 
-    let mut piece_textures = HashMap::<PieceType, Vec<Texture2D>>::new();
-        for file in glob("./imgs/pieces-basic-png/*").expect("D.piece_rect.ry not found") {
-            let f = file.unwrap();
-            let texture = rl.load_texture(
-                &thread,
-                f.clone().into_os_string().to_str().expect("failed"),
-            );
-            let t = texture.unwrap();
+    let depth = match args.first().and_then(|depth| depth.parse::<u32>().ok()) {
+        Some(depth) => depth,
+        None => {
+            eprintln!("Usage: rusty_chess perft [divide] <depth> [FEN]");
+            return;
+        }
+    };
+    let fen = if args.len() > 1 { args[1..].join(" ") } else { STARTING_FEN.to_string() };
+    let position = match Position::from_fen(&fen) {
+        Ok(position) => position,
+        Err(e) => {
+            eprintln!("Invalid FEN: {}", e);
+            return;
+        }
+    };
 
-            let path_substring = &(f.clone().into_os_string().into_string().unwrap());
+    let start = Instant::now();
+    let nodes = if divide {
+        let mut counts: Vec<(String, u64)> = position
+            .perft_divide(depth)
+            .into_iter()
+            .map(|(mv, count)| (mv.to_uci(), count))
+            .collect();
+        counts.sort();
+        for (mv, count) in &counts {
+            println!("{}: {}", mv, count);
+        }
+        println!();
+        counts.iter().map(|(_, count)| count).sum()
+    } else {
+        position.perft(depth)
+    };
+    println!("Nodes searched: {}", nodes);
+    println!("Time: {} ms", start.elapsed().as_millis());
+}
 
-            let (index, _owner) = piece_index(&String::from(path_substring));
+// Pixels a square takes up on screen, for drawing SVG pieces at
+fn piece_size(rl: &RaylibHandle) -> i32 {
+    let viewport = Viewport::fit(rl.get_screen_width(), rl.get_screen_height());
+    (SPACE_SIZE * viewport.scale * rl.get_window_scale_dpi().x).ceil() as i32
+}
 
-            let name = string_to_piece_name(&path_substring[index? + 6..path_substring.len() - 4]);
+fn main() -> Result<(), Error> {
+    // `--fen "<FEN>"` skips the menu and starts playing from that position,
+    // `--pgn <file>` loads a recorded game to replay and `--uci` runs the
+    // engine over stdin/stdout for chess GUIs, without opening a window.
+    // `--themes <dir>` and `--theme <name>` pick where piece sets come from.
+    // `--book <file>` gives the computer a Polyglot opening book to play from,
+    // `--syzygy <dir>` a directory of Syzygy endgame tables.
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--uci") {
+        if let Err(e) = uci::run(io::stdin().lock(), io::stdout()) {
+            eprintln!("UCI session ended: {}", e);
+        }
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("perft") {
+        run_perft(&args[2..]);
+        return Ok(());
+    }
 
-            let p_name = &name.unwrap();
+    let option = |name: &str| {
+        args.iter().position(|arg| arg == name).map(|i| args.get(i + 1).cloned().unwrap_or_default())
+    };
 
-            match piece_textures.entry(*p_name) {
-                std::collections::hash_map::Entry::Occupied(mut entry) => {
-                    entry.get_mut().push(t);
-                }
+    let book = match option("--book") {
+        Some(path) => match Book::open(Path::new(&path)) {
+            Ok(book) => Some(Rc::new(book)),
+            Err(e) => {
+                eprintln!("Could not load opening book {}: {}", path, e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+    let tablebases = match option("--syzygy") {
+        Some(path) => match Tablebases::open(Path::new(&path)) {
+            Ok(tablebases) => Some(Arc::new(tablebases)),
+            Err(e) => {
+                eprintln!("Could not load tablebases from {}: {}", path, e);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
 
-                std::collections::hash_map::Entry::Vacant(_entry) => {
-                    let mut texture_vec = Vec::<Texture2D>::new();
-                    texture_vec.push(t);
-                    piece_textures.insert(*p_name, texture_vec);
-                }
+    let mut g = game::Game::default();
+    if let Some(fen) = option("--fen") {
+        g = match game::Game::from_fen(&fen) {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!("Invalid FEN: {}", e);
+                return Ok(());
             }
-        }
-        Ok(piece_textures)
-}
+        };
+        g.state = GameState::Playing;
+    } else if let Some(path) = option("--pgn") {
+        let pgn = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Pgn::parse(&text).map_err(|e| e.to_string()));
+        g = match pgn {
+            Ok(pgn) => game::Game::from_pgn(&pgn),
+            Err(e) => {
+                eprintln!("Could not load {}: {}", path, e);
+                return Ok(());
+            }
+        };
+        g.state = GameState::Playing;
+    }
 
-fn main() -> Result<(), Error> {
+    g.book = book.clone();
+    g.tablebases = tablebases.clone();
+
+    // Render at the display's full resolution on high-DPI screens. The builder
+    // has no option for it, but raylib adds these flags to the builder's own.
+    unsafe {
+        raylib::ffi::SetConfigFlags(ConfigFlags::FLAG_WINDOW_HIGHDPI as u32);
+    }
     let (mut rl, thread) = raylib::init()
         .size(WIDTH, HEIGHT)
+        .resizable()
+        .msaa_4x()
         .title("Rusty Chess")
         .build();
+    rl.set_window_min_size(WIDTH / 2, HEIGHT / 2);
+    // Escape leaves the move box rather than closing the window
+    rl.set_exit_key(None);
 
-    let piece_textures = load_pieces_textures(&mut rl, thread.clone()).unwrap();
-    let mut g = game::Game::default();
+    let themes_dir = option("--themes").map_or(PathBuf::from(DEFAULT_THEME_DIR), PathBuf::from);
+    let theme = option("--theme").unwrap_or(DEFAULT_THEME.to_string());
+    let size = piece_size(&rl);
+    let mut pieces = match PieceSet::load(&mut rl, &thread, &themes_dir.join(&theme), size) {
+        Ok(pieces) => pieces,
+        Err(e) => {
+            eprintln!("Could not load piece theme {}: {}", theme, e);
+            return Ok(());
+        }
+    };
+    let mut main_menu = menu::MainMenu::new();
+    let mut settings = settings::Settings::new(themes_dir);
+    let mut editor = editor::Editor::new();
 
     while !(rl.window_should_close()) {
-        let d: &mut RaylibDrawHandle<'_> = &mut rl.begin_drawing(&thread);
+        // Everything below is drawn and hit-tested as if the window were still WIDTH x HEIGHT
+        let viewport = Viewport::fit(rl.get_screen_width(), rl.get_screen_height());
+        viewport.apply_to_mouse(&mut rl);
+
+        // SVG themes are drawn again to stay sharp at the new size
+        let size = piece_size(&rl);
+        if rl.is_window_resized() && pieces.needs_redraw(size) {
+            let dir = settings.themes_dir.join(pieces.name());
+            match PieceSet::load(&mut rl, &thread, &dir, size) {
+                Ok(redrawn) => pieces = redrawn,
+                Err(e) => eprintln!("Could not redraw piece theme: {}", e),
+            }
+        }
+
+        let mut drawing = rl.begin_drawing(&thread);
+        drawing.clear_background(Color::WHITE);
+        let d: &mut RaylibDrawHandle<'_> = &mut drawing.begin_mode2D(viewport.camera());
         match g.state {
-            1 => menu::create_menu(d, &mut g.state),
-            0 => g.run(d, thread.clone(), &piece_textures)?,
-            _ => print!("asd"), 
+            GameState::Menu => {
+                menu::create_menu(d, &mut g.state, &mut main_menu);
+                if g.state == GameState::Settings {
+                    settings.refresh();
+                }
+                if g.state == GameState::Playing {
+                    g.book = book.clone();
+                    g.tablebases = tablebases.clone();
+                    if main_menu.analysis() {
+                        g.start_analysis();
+                    } else {
+                        g.set_opponent(main_menu.opponent());
+                        g.set_time_control(main_menu.time_control());
+                    }
+                    if let Some((role, address)) = main_menu.network() {
+                        let network = match (role, g.opponent) {
+                            (NetworkRole::Host, Opponent::Remote { colour }) => {
+                                let new_game = Message::NewGame { guest_colour: colour, time_control: g.clock.map(|clock| clock.control()), start: g.position };
+                                Network::host(address, new_game).map_err(|e| format!("Could not host a game: {}", e))
+                            }
+                            _ => Network::join(address).map_err(|e| format!("Could not join {}: {}", address, e)),
+                        };
+                        match network {
+                            Ok(network) => g.network = Some(network),
+                            Err(e) => {
+                                main_menu.error = Some(e);
+                                g = game::Game::default();
+                            }
+                        }
+                    }
+                }
+            }
+            GameState::Settings => {
+                if let Some(theme) = settings::create_settings(d, &mut g.state, &mut settings, &pieces) {
+                    match PieceSet::load(d, &thread, &settings.themes_dir.join(&theme), size) {
+                        Ok(picked) => {
+                            pieces = picked;
+                            settings.error = None;
+                        }
+                        Err(e) => settings.error = Some(e.to_string()),
+                    }
+                }
+            }
+            GameState::Editor => {
+                if let Some(action) = editor::create_editor(d, &mut g.state, &mut editor, &pieces) {
+                    let analysing = matches!(action, EditorAction::Analyse(_));
+                    let (EditorAction::Play(position) | EditorAction::Analyse(position)) = action;
+                    g = game::Game::from_position(position);
+                    g.state = GameState::Playing;
+                    g.book = book.clone();
+                    g.tablebases = tablebases.clone();
+                    if analysing {
+                        g.start_analysis();
+                    } else {
+                        // A set up position is played at the one board, or against the computer
+                        let opponent = match main_menu.opponent() {
+                            Opponent::Remote { .. } => Opponent::Human,
+                            opponent => opponent,
+                        };
+                        g.set_opponent(opponent);
+                        g.set_time_control(main_menu.time_control());
+                    }
+                }
+            }
+            GameState::Playing => g.run(d, thread.clone(), &pieces)?,
         }
-        d.clear_background(Color::WHITE);
     }
 
     Ok(())
@@ -0,0 +1,388 @@
+// Playing a game between two machines over TCP.
+//
+// One side hosts and the other joins. They then exchange messages, one per
+// line of UTF-8 text with space-separated fields:
+//
+//     hello <version>                     both sides, straight after connecting
+//     new <colour> <clock> <FEN>          host to guest: the colour the guest
+//                                         plays, the time control and the start
+//     move <move> <time left>             a move in UCI notation like e7e8q, and
+//                                         the mover's clock in ms after it, or -
+//     resign
+//     draw offer | draw accept | draw decline
+//
+// `<clock>` is one of `untimed`, `sudden <base>`, `fischer <base> <increment>`
+// or `bronstein <base> <delay>`, all in milliseconds. Closing the connection
+// abandons the game. Nothing a peer sends is trusted: moves have to be legal
+// in the receiver's own copy of the game before they are played.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::board::movegen::Move;
+use crate::board::piece::Owner;
+use crate::board::position::Position;
+use crate::clock::TimeControl;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7878;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Hello { version: u32 },
+    NewGame { guest_colour: Owner, time_control: Option<TimeControl>, start: Position },
+    // Not checked against any position yet
+    Move { mv: Move, time_left: Option<Duration> },
+    Resign,
+    DrawOffer,
+    DrawAccept,
+    DrawDecline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    Malformed(String),
+    WrongVersion(u32),
+    IllegalMove(String),
+    OutOfTurn,
+    // Something about the game came before the game itself
+    NotStarted,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Malformed(line) => write!(f, "could not understand \"{}\"", line),
+            ProtocolError::WrongVersion(version) => write!(f, "other side speaks version {}, not {}", version, PROTOCOL_VERSION),
+            ProtocolError::IllegalMove(mv) => write!(f, "other side played an illegal move: {}", mv),
+            ProtocolError::OutOfTurn => write!(f, "other side moved out of turn"),
+            ProtocolError::NotStarted => write!(f, "other side played before the game started"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+fn write_millis(f: &mut fmt::Formatter, time: Duration) -> fmt::Result {
+    write!(f, " {}", time.as_millis())
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello { version } => write!(f, "hello {}", version),
+            Message::NewGame { guest_colour, time_control, start } => {
+                let colour = match guest_colour {
+                    Owner::White => "white",
+                    Owner::Black => "black",
+                };
+                write!(f, "new {} ", colour)?;
+                match *time_control {
+                    None => write!(f, "untimed")?,
+                    Some(TimeControl::SuddenDeath { base }) => {
+                        write!(f, "sudden")?;
+                        write_millis(f, base)?;
+                    }
+                    Some(TimeControl::Fischer { base, increment }) => {
+                        write!(f, "fischer")?;
+                        write_millis(f, base)?;
+                        write_millis(f, increment)?;
+                    }
+                    Some(TimeControl::Bronstein { base, delay }) => {
+                        write!(f, "bronstein")?;
+                        write_millis(f, base)?;
+                        write_millis(f, delay)?;
+                    }
+                }
+                write!(f, " {}", start.to_fen())
+            }
+            Message::Move { mv, time_left } => {
+                write!(f, "move {}", mv.to_uci())?;
+                match time_left {
+                    Some(time) => write_millis(f, *time),
+                    None => write!(f, " -"),
+                }
+            }
+            Message::Resign => write!(f, "resign"),
+            Message::DrawOffer => write!(f, "draw offer"),
+            Message::DrawAccept => write!(f, "draw accept"),
+            Message::DrawDecline => write!(f, "draw decline"),
+        }
+    }
+}
+
+impl Message {
+    pub fn parse(line: &str) -> Result<Message, ProtocolError> {
+        let malformed = || ProtocolError::Malformed(line.to_string());
+        let mut fields = line.split_whitespace();
+        let millis = |fields: &mut std::str::SplitWhitespace| {
+            fields.next().and_then(|field| field.parse().ok()).map(Duration::from_millis).ok_or_else(malformed)
+        };
+
+        let message = match (fields.next(), fields.next()) {
+            (Some("hello"), Some(version)) => Message::Hello { version: version.parse().map_err(|_| malformed())? },
+            (Some("new"), Some(colour)) => {
+                let guest_colour = match colour {
+                    "white" => Owner::White,
+                    "black" => Owner::Black,
+                    _ => return Err(malformed()),
+                };
+                let time_control = match fields.next() {
+                    Some("untimed") => None,
+                    Some("sudden") => Some(TimeControl::SuddenDeath { base: millis(&mut fields)? }),
+                    Some("fischer") => Some(TimeControl::Fischer { base: millis(&mut fields)?, increment: millis(&mut fields)? }),
+                    Some("bronstein") => Some(TimeControl::Bronstein { base: millis(&mut fields)?, delay: millis(&mut fields)? }),
+                    _ => return Err(malformed()),
+                };
+                let fen = fields.by_ref().collect::<Vec<_>>().join(" ");
+                // A start that couldn't come up in a game would corrupt everything played from it
+                let start = Position::from_fen(&fen).map_err(|_| malformed())?;
+                start.validate().map_err(|_| malformed())?;
+                Message::NewGame { guest_colour, time_control, start }
+            }
+            (Some("move"), Some(mv)) => {
+                let mv = Move::from_uci(mv).ok_or_else(malformed)?;
+                let time_left = match fields.next() {
+                    Some("-") => None,
+                    Some(time) => Some(Duration::from_millis(time.parse().map_err(|_| malformed())?)),
+                    None => return Err(malformed()),
+                };
+                Message::Move { mv, time_left }
+            }
+            (Some("resign"), None) => Message::Resign,
+            (Some("draw"), Some("offer")) => Message::DrawOffer,
+            (Some("draw"), Some("accept")) => Message::DrawAccept,
+            (Some("draw"), Some("decline")) => Message::DrawDecline,
+            _ => return Err(malformed()),
+        };
+
+        if fields.next().is_some() {
+            return Err(malformed());
+        }
+        Ok(message)
+    }
+}
+
+// Moves from the other side go through this before they are played
+pub fn check_move(position: &Position, mv: Move) -> Result<Move, ProtocolError> {
+    if position.is_legal(mv) {
+        Ok(mv)
+    } else {
+        Err(ProtocolError::IllegalMove(mv.to_uci()))
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Message(Message),
+    Error(ProtocolError),
+    // The other side went away, or the connection failed
+    Closed,
+}
+
+// A connection to the other player. Messages are read and written on threads
+// of their own, so neither checking for them nor sending them ever blocks.
+pub struct Peer {
+    stream: TcpStream,
+    events: Receiver<Event>,
+    outgoing: Sender<Message>,
+}
+
+impl Peer {
+    pub fn connect(address: &str) -> io::Result<Peer> {
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to"))?;
+        Peer::new(TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?)
+    }
+
+    fn new(stream: TcpStream) -> io::Result<Peer> {
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream.try_clone()?;
+        let (sender, events) = mpsc::channel();
+        let (outgoing, to_send) = mpsc::channel::<Message>();
+
+        thread::spawn(move || {
+            let mut greeted = false;
+            for line in reader.lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                // The hello is dealt with here, the game only hears about what follows
+                let event = match Message::parse(&line) {
+                    Ok(Message::Hello { version }) if version != PROTOCOL_VERSION => Event::Error(ProtocolError::WrongVersion(version)),
+                    Ok(Message::Hello { .. }) if !greeted => {
+                        greeted = true;
+                        continue;
+                    }
+                    Ok(_) if !greeted => Event::Error(ProtocolError::Malformed(line)),
+                    Ok(message) => Event::Message(message),
+                    Err(e) => Event::Error(e),
+                };
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Event::Closed);
+        });
+
+        // A peer that stops reading only holds up this thread. If a write
+        // fails the connection is shut, which the reading thread reports.
+        thread::spawn(move || {
+            for message in to_send {
+                if writeln!(writer, "{}", message).and_then(|()| writer.flush()).is_err() {
+                    let _ = writer.shutdown(Shutdown::Both);
+                    return;
+                }
+            }
+        });
+
+        let mut peer = Peer { stream, events, outgoing };
+        peer.send(&Message::Hello { version: PROTOCOL_VERSION })?;
+        Ok(peer)
+    }
+
+    // Queues the message to be written. It only fails once writing already has.
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        self.outgoing
+            .send(message.clone())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the connection is closed"))
+    }
+
+    // The next thing the other side did, if anything
+    pub fn poll(&self) -> Option<Event> {
+        match self.events.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Event::Closed),
+        }
+    }
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        // Ends the reading thread. The writing thread still sends whatever is
+        // queued, and the connection closes once both threads are done.
+        let _ = self.stream.shutdown(Shutdown::Read);
+    }
+}
+
+// Waits for someone to join, without blocking
+pub struct Host {
+    listener: TcpListener,
+}
+
+impl Host {
+    pub fn listen(address: &str) -> io::Result<Host> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Host { listener })
+    }
+
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    pub fn accept(&self) -> io::Result<Option<Peer>> {
+        match self.listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                Peer::new(stream).map(Some)
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::square::Square;
+    use std::time::Instant;
+
+    fn wait_for(peer: &Peer) -> Event {
+        let start = Instant::now();
+        loop {
+            if let Some(event) = peer.poll() {
+                return event;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "nothing arrived");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let messages = [
+            Message::NewGame {
+                guest_colour: Owner::Black,
+                time_control: Some(TimeControl::Fischer { base: Duration::from_secs(180), increment: Duration::from_secs(2) }),
+                start: Position::default(),
+            },
+            Message::Move { mv: Move::from_uci("e7e8q").unwrap(), time_left: Some(Duration::from_millis(61234)) },
+            Message::Move { mv: Move::from_uci("g1f3").unwrap(), time_left: None },
+            Message::DrawOffer,
+        ];
+        for message in messages {
+            assert_eq!(Message::parse(&message.to_string()), Ok(message));
+        }
+
+        assert!(Message::parse("move e2").is_err());
+        assert!(Message::parse("resign now").is_err());
+        // Starts that read as FEN but can't be played from
+        assert!(Message::parse("new black untimed 4k3/8/8/8/8/8/8/3KK3 w - - 0 1").is_err());
+        assert!(Message::parse("new black untimed 4k2P/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn test_illegal_move_rejected() {
+        let position = Position::default();
+        assert!(check_move(&position, Move::new(Square::parse("e2").unwrap(), Square::parse("e4").unwrap())).is_ok());
+        assert_eq!(
+            check_move(&position, Move::new(Square::parse("e2").unwrap(), Square::parse("e5").unwrap())),
+            Err(ProtocolError::IllegalMove("e2e5".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_loopback_game() {
+        let host = Host::listen("127.0.0.1:0").unwrap();
+        let mut guest = Peer::connect(&format!("127.0.0.1:{}", host.port().unwrap())).unwrap();
+
+        let start = Instant::now();
+        let mut hosted = loop {
+            if let Some(peer) = host.accept().unwrap() {
+                break peer;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "nobody joined");
+            thread::sleep(Duration::from_millis(5));
+        };
+
+        let new_game = Message::NewGame { guest_colour: Owner::Black, time_control: None, start: Position::default() };
+        hosted.send(&new_game).unwrap();
+        assert!(matches!(wait_for(&guest), Event::Message(message) if message == new_game));
+
+        guest.send(&Message::Resign).unwrap();
+        assert!(matches!(wait_for(&hosted), Event::Message(Message::Resign)));
+
+        // What was sent just before leaving still gets there
+        guest.send(&Message::DrawOffer).unwrap();
+        drop(guest);
+        assert!(matches!(wait_for(&hosted), Event::Message(Message::DrawOffer)));
+        assert!(matches!(wait_for(&hosted), Event::Closed));
+    }
+}
@@ -0,0 +1,139 @@
+use rusty_chess::board::piece::{Owner, Piece, PieceType};
+use raylib::prelude::*;
+use resvg::{tiny_skia, usvg};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Themes are the directories in here, unless `--themes <dir>` says otherwise
+pub const DEFAULT_THEME_DIR: &str = "./imgs";
+pub const DEFAULT_THEME: &str = "pieces-basic-png";
+
+#[derive(Debug)]
+pub enum ThemeError {
+    // Neither a PNG nor an SVG for the piece, the path being the PNG one
+    MissingFile(PathBuf),
+    Unreadable { path: PathBuf, reason: String },
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::MissingFile(path) => write!(f, "missing {} (or .svg)", path.display()),
+            ThemeError::Unreadable { path, reason } => write!(f, "could not load {}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+// A set of piece images, one for each colour and type, named like "white-knight.png"
+pub struct PieceSet {
+    name: String,
+    textures: HashMap<Piece, Texture2D>,
+    // Pixel size SVGs were drawn at, None when every image is a PNG
+    svg_size: Option<i32>,
+}
+
+impl PieceSet {
+    // Load the theme in `dir`, drawing any SVGs `size` pixels square
+    pub fn load(rl: &mut RaylibHandle, thread: &RaylibThread, dir: &Path, size: i32) -> Result<PieceSet, ThemeError> {
+        let mut textures = HashMap::new();
+        let mut svg_size = None;
+
+        for owner in [Owner::White, Owner::Black] {
+            for piece_type in PieceType::ALL {
+                let piece = Piece::new(owner, piece_type);
+                let png = dir.join(format!("{}.png", file_stem(piece)));
+                let svg = png.with_extension("svg");
+
+                let (path, image) = if png.exists() {
+                    let image = Image::load_image(&png.to_string_lossy()).map_err(|e| e.to_string());
+                    (png, image)
+                } else if svg.exists() {
+                    svg_size = Some(size);
+                    let image = rasterize_svg(&svg, size);
+                    (svg, image)
+                } else {
+                    return Err(ThemeError::MissingFile(png));
+                };
+
+                let mut texture = image
+                    .and_then(|image| rl.load_texture_from_image(thread, &image).map_err(|e| e.to_string()))
+                    .map_err(|reason| ThemeError::Unreadable { path, reason })?;
+                // The pieces are drawn at whatever size the window calls for
+                texture.gen_texture_mipmaps();
+                texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_TRILINEAR);
+                textures.insert(piece, texture);
+            }
+        }
+
+        let name = dir.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        Ok(PieceSet { name, textures, svg_size })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn texture(&self, piece: Piece) -> &Texture2D {
+        // `load` fails unless every piece has one
+        &self.textures[&piece]
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, piece: Piece, dest: Rectangle) {
+        let texture = self.texture(piece);
+        let source = Rectangle { x: 0.0, y: 0.0, width: texture.width() as f32, height: texture.height() as f32 };
+        d.draw_texture_pro(texture, source, dest, Vector2 { x: 0.0, y: 0.0 }, 0.0, Color::WHITE);
+    }
+
+    // Whether drawing the SVGs again at `size` would make them any sharper
+    pub fn needs_redraw(&self, size: i32) -> bool {
+        self.svg_size.is_some_and(|svg_size| svg_size != size)
+    }
+}
+
+// The themes to choose from, by directory name
+pub fn available_themes(dir: &Path) -> Vec<String> {
+    let mut themes: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    themes.sort();
+    themes
+}
+
+fn file_stem(piece: Piece) -> String {
+    let colour = match piece.owner {
+        Owner::White => "white",
+        Owner::Black => "black",
+    };
+    let name = match piece.piece_type {
+        PieceType::Pawn => "pawn",
+        PieceType::Rook => "rook",
+        PieceType::Knight => "knight",
+        PieceType::Bishop => "bishop",
+        PieceType::Queen => "queen",
+        PieceType::King => "king",
+    };
+    format!("{}-{}", colour, name)
+}
+
+// raylib can't read SVGs, so they are drawn to a PNG in memory first
+fn rasterize_svg(path: &Path, size: i32) -> Result<Image, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let tree = usvg::Tree::from_data(&data, &usvg::Options::default()).map_err(|e| e.to_string())?;
+    let mut pixmap = tiny_skia::Pixmap::new(size as u32, size as u32).ok_or("size is zero")?;
+
+    let scale = size as f32 / tree.size().width().max(tree.size().height());
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    let png = pixmap.encode_png().map_err(|e| e.to_string())?;
+    Image::load_image_from_mem(".png", &png).map_err(|e| e.to_string())
+}